@@ -0,0 +1,373 @@
+//! Local HTTP/JSON API
+//! Opt-in through `settings.api`. Listens on 127.0.0.1 only, and every
+//! request needs `Authorization: Bearer <token>`, where the token is read
+//! from `api-token` next to `config.json` (generated on first start).
+//!
+//! Projects are addressed by id or name. Endpoints that act on a process
+//! take an optional `?service=` for projects with services:
+//!
+//! - `GET /api/projects`, `POST /api/projects` (`{"name", "path", "commands"}`)
+//! - `GET`, `PUT` and `DELETE /api/projects/{id}`
+//! - `POST /api/projects/{id}/start`, `/stop` and `/restart`
+//! - `GET /api/projects/{id}/status` (with the circuit breaker) and `/logs`
+//! - `GET /api/projects/{id}/logs/page?limit=` with optional `cursor` and
+//!   `direction` (`before` or `after`)
+//! - `POST /api/projects/{id}/input` (`{"input": "..."}`)
+//! - `GET /api/events`, a WebSocket relaying `process-log`, `process-status`,
+//!   `process-crash` and `process-breaker` as `{"event": "...", "payload":
+//!   {...}}` text frames.
+//!   Browsers cannot set headers on a WebSocket, so it also accepts
+//!   `?token=`.
+//!
+//! Errors are `{"error": "..."}` with a 4xx status.
+
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::commands::AppState;
+use crate::config::{self, Project};
+use crate::dependency;
+use crate::process_manager::{LogDirection, LogEntry, LogPage};
+
+/// Events relayed over `/api/events`
+const RELAYED_EVENTS: [&str; 4] = ["process-log", "process-status", "process-crash", "process-breaker"];
+
+#[derive(Clone)]
+struct Api {
+    state: AppState,
+    token: String,
+}
+
+struct ApiError(StatusCode, String);
+
+impl ApiError {
+    fn bad_request(message: String) -> Self {
+        Self(StatusCode::BAD_REQUEST, message)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+type ApiResult<T> = Result<T, ApiError>;
+
+#[derive(Debug, Deserialize)]
+struct ServiceQuery {
+    service: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PageQuery {
+    service: Option<String>,
+    cursor: Option<u64>,
+    direction: Option<LogDirection>,
+    limit: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct NewProject {
+    name: String,
+    path: String,
+    commands: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Input {
+    input: String,
+}
+
+/// `api-token` next to `config.json`
+pub fn token_path() -> PathBuf {
+    config::get_config_path().with_file_name("api-token")
+}
+
+/// Read the API token, generating one readable only by the owner if there
+/// is none yet
+fn load_or_create_token() -> Result<String, String> {
+    let path = token_path();
+    if let Ok(token) = std::fs::read_to_string(&path) {
+        let token = token.trim();
+        if !token.is_empty() {
+            return Ok(token.to_string());
+        }
+    }
+
+    let token = format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(&path)
+        .map_err(|e| format!("Failed to create '{}': {}", path.display(), e))?;
+    std::io::Write::write_all(&mut file, token.as_bytes())
+        .map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+    Ok(token)
+}
+
+/// Serve the API on 127.0.0.1:`port` in the background. Failing to bind
+/// only disables the API.
+pub fn start(state: AppState, port: u16) {
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = serve(state, port).await {
+            eprintln!("HTTP API unavailable: {}", e);
+        }
+    });
+}
+
+async fn serve(state: AppState, port: u16) -> Result<(), String> {
+    let token = load_or_create_token()?;
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .map_err(|e| format!("Failed to listen on {}: {}", addr, e))?;
+    println!("HTTP API listening on http://{}", addr);
+
+    axum::serve(listener, router(Api { state, token }))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+fn router(api: Api) -> Router {
+    Router::new()
+        .route("/api/projects", get(list_projects).post(add_project))
+        .route(
+            "/api/projects/{id}",
+            get(get_project).put(update_project).delete(delete_project),
+        )
+        .route("/api/projects/{id}/start", post(start_project))
+        .route("/api/projects/{id}/stop", post(stop_project))
+        .route("/api/projects/{id}/restart", post(restart_project))
+        .route("/api/projects/{id}/status", get(project_status))
+        .route("/api/projects/{id}/logs", get(project_logs))
+        .route("/api/projects/{id}/logs/page", get(project_log_page))
+        .route("/api/projects/{id}/input", post(project_input))
+        .route("/api/events", get(events))
+        .layer(middleware::from_fn_with_state(api.clone(), authorize))
+        .with_state(api)
+}
+
+/// Reject requests without the bearer token (or `?token=` on the WebSocket)
+async fn authorize(State(api): State<Api>, request: Request, next: Next) -> Response {
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+    let query = (request.uri().path() == "/api/events")
+        .then(|| request.uri().query())
+        .flatten()
+        .and_then(|query| query.split('&').find_map(|pair| pair.strip_prefix("token=")));
+
+    match bearer.or(query) {
+        Some(token) if constant_time_eq(token.as_bytes(), api.token.as_bytes()) => next.run(request).await,
+        _ => ApiError(StatusCode::UNAUTHORIZED, "Missing or invalid API token".to_string()).into_response(),
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Run an `AppState` method off the async workers, since they may block
+async fn blocking<T, F>(api: &Api, f: F) -> ApiResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&AppState) -> Result<T, String> + Send + 'static,
+{
+    let state = api.state.clone();
+    tokio::task::spawn_blocking(move || f(&state))
+        .await
+        .map_err(|e| ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(ApiError::bad_request)
+}
+
+/// The project with this id or name
+fn find(api: &Api, id: &str) -> ApiResult<Project> {
+    let config = api.state.config.lock().unwrap();
+    dependency::find_project(&config.projects, id)
+        .cloned()
+        .ok_or_else(|| ApiError(StatusCode::NOT_FOUND, format!("Project not found: {}", id)))
+}
+
+// ============ Projects ============
+
+async fn list_projects(State(api): State<Api>) -> Json<Vec<Project>> {
+    Json(api.state.get_projects())
+}
+
+async fn add_project(State(api): State<Api>, Json(new): Json<NewProject>) -> ApiResult<impl IntoResponse> {
+    let project = blocking(&api, move |state| state.add_project(new.name, new.path, new.commands)).await?;
+    Ok((StatusCode::CREATED, Json(project)))
+}
+
+async fn get_project(State(api): State<Api>, Path(id): Path<String>) -> ApiResult<Json<Project>> {
+    find(&api, &id).map(Json)
+}
+
+/// Replace a project; its id comes from the path
+async fn update_project(
+    State(api): State<Api>,
+    Path(id): Path<String>,
+    Json(mut project): Json<Project>,
+) -> ApiResult<Json<Project>> {
+    project.id = find(&api, &id)?.id;
+    let updated = project.clone();
+    blocking(&api, move |state| state.update_project(project)).await?;
+    Ok(Json(updated))
+}
+
+async fn delete_project(State(api): State<Api>, Path(id): Path<String>) -> ApiResult<StatusCode> {
+    let id = find(&api, &id)?.id;
+    api.state.delete_project(id).await.map_err(ApiError::bad_request)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ============ Processes ============
+
+async fn start_project(
+    State(api): State<Api>,
+    Path(id): Path<String>,
+    Query(query): Query<ServiceQuery>,
+) -> ApiResult<StatusCode> {
+    let id = find(&api, &id)?.id;
+    blocking(&api, move |state| match query.service {
+        Some(service) => state.start_service(id, service),
+        None => state.start_project(id),
+    })
+    .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn stop_project(
+    State(api): State<Api>,
+    Path(id): Path<String>,
+    Query(query): Query<ServiceQuery>,
+) -> ApiResult<StatusCode> {
+    let id = find(&api, &id)?.id;
+    match query.service {
+        Some(service) => api.state.stop_service(id, service).await,
+        None => api.state.stop_project(id).await,
+    }
+    .map_err(ApiError::bad_request)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn restart_project(
+    State(api): State<Api>,
+    Path(id): Path<String>,
+    Query(query): Query<ServiceQuery>,
+) -> ApiResult<StatusCode> {
+    let id = find(&api, &id)?.id;
+    match query.service {
+        Some(service) => api.state.restart_service(id, service).await,
+        None => api.state.restart_project(id).await,
+    }
+    .map_err(ApiError::bad_request)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Status and circuit breaker of the project or service, plus each
+/// service's status
+async fn project_status(
+    State(api): State<Api>,
+    Path(id): Path<String>,
+    Query(query): Query<ServiceQuery>,
+) -> ApiResult<Json<Value>> {
+    let id = find(&api, &id)?.id;
+    let status = api.state.get_project_status(id.clone(), query.service.clone());
+    let services = api.state.get_service_statuses(id.clone());
+    let breaker = api.state.get_breaker_status(id.clone(), query.service.clone());
+    Ok(Json(json!({
+        "project_id": id,
+        "service": query.service,
+        "status": status,
+        "services": services,
+        "breaker": breaker,
+    })))
+}
+
+async fn project_logs(
+    State(api): State<Api>,
+    Path(id): Path<String>,
+    Query(query): Query<ServiceQuery>,
+) -> ApiResult<Json<Vec<LogEntry>>> {
+    let id = find(&api, &id)?.id;
+    Ok(Json(api.state.get_project_logs(id, query.service)))
+}
+
+async fn project_log_page(
+    State(api): State<Api>,
+    Path(id): Path<String>,
+    Query(query): Query<PageQuery>,
+) -> ApiResult<Json<LogPage>> {
+    let id = find(&api, &id)?.id;
+    Ok(Json(api.state.get_log_page(id, query.service, query.cursor, query.direction, query.limit)))
+}
+
+async fn project_input(
+    State(api): State<Api>,
+    Path(id): Path<String>,
+    Query(query): Query<ServiceQuery>,
+    Json(input): Json<Input>,
+) -> ApiResult<StatusCode> {
+    let id = find(&api, &id)?.id;
+    api.state
+        .send_project_input(id, query.service, input.input)
+        .await
+        .map_err(ApiError::bad_request)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ============ Events ============
+
+async fn events(State(api): State<Api>, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(move |socket| relay_events(api, socket))
+}
+
+/// Forward process events until the client goes away. A client that falls
+/// behind receives `{"event": "lagged", "payload": {"missed": n}}`.
+async fn relay_events(api: Api, mut socket: WebSocket) {
+    let mut events = api.state.process_manager.subscribe();
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) if RELAYED_EVENTS.contains(&event.event.as_str()) => event,
+                Ok(_) => continue,
+                Err(RecvError::Lagged(missed)) => crate::events::Event {
+                    event: "lagged".to_string(),
+                    payload: json!({ "missed": missed }),
+                },
+                Err(RecvError::Closed) => break,
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+        };
+        let Ok(text) = serde_json::to_string(&event) else {
+            continue;
+        };
+        if socket.send(Message::Text(text.into())).await.is_err() {
+            break;
+        }
+    }
+}
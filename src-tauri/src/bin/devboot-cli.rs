@@ -0,0 +1,5 @@
+// DevBoot command-line client
+
+fn main() {
+    devboot_lib::run_cli()
+}
@@ -0,0 +1,5 @@
+// Headless DevBoot: supervises projects without a window
+
+fn main() {
+    devboot_lib::run_daemon()
+}
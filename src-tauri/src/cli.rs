@@ -0,0 +1,614 @@
+//! Command-line client (`devboot-cli`)
+//! Talks to the running app or `devbootd` over the control socket, and
+//! reads or edits `config.json` directly when neither is running.
+//! It is not called `devboot` because that is the app's own binary.
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use crate::config::{self, Project};
+use crate::dependency;
+use crate::detector::{self, DetectedProject};
+use crate::log_search::{LogQuery, LogSearch, Search};
+use crate::log_store;
+use crate::process_manager::LogEntry;
+
+const USAGE: &str = "Usage: devboot-cli [--json] [--socket <path>] <command>
+
+Commands:
+  list                                 Projects and their status
+  status [<project>]                   Status of one project and its services
+  start <project> [--service <name>]
+  stop <project> [--service <name>]
+  restart <project> [--service <name>]
+  logs <project> [--service <name>] [--follow]
+  send <project> [--service <name>] <input>...
+  search <pattern> [--regex] [--case-sensitive] [--project <project>]...
+         [--stream <stdout|stderr|stdin|system>]... [--since <time>]
+         [--until <time>] [--context <lines>] [--limit <matches>]
+  add <path> [--name <name>] [--command <command>]...
+  detect <path>
+
+Projects are given by name or id. Without a running app or devbootd,
+list, status and add use config.json directly, and logs and search read
+the log files of the last session. Times are RFC 3339, e.g.
+2024-05-01T09:30:00+02:00.";
+
+/// Parsed command line
+#[derive(Default)]
+struct Args {
+    help: bool,
+    json: bool,
+    follow: bool,
+    socket: Option<PathBuf>,
+    service: Option<String>,
+    name: Option<String>,
+    commands: Vec<String>,
+    regex: bool,
+    case_sensitive: bool,
+    projects: Vec<String>,
+    streams: Vec<String>,
+    since: Option<String>,
+    until: Option<String>,
+    context: Option<usize>,
+    limit: Option<usize>,
+    positional: Vec<String>,
+}
+
+impl Args {
+    fn parse(raw: Vec<String>) -> Result<Self, String> {
+        let mut args = Args::default();
+        let mut raw = raw.into_iter();
+        while let Some(arg) = raw.next() {
+            let mut value = || raw.next().ok_or_else(|| format!("{} needs a value", arg));
+            match arg.as_str() {
+                "-h" | "--help" => args.help = true,
+                "--json" => args.json = true,
+                "-f" | "--follow" => args.follow = true,
+                "--socket" => args.socket = Some(value()?.into()),
+                "--service" => args.service = Some(value()?),
+                "--name" => args.name = Some(value()?),
+                "--command" => args.commands.push(value()?),
+                "--regex" => args.regex = true,
+                "--case-sensitive" => args.case_sensitive = true,
+                "--project" => args.projects.push(value()?),
+                "--stream" => args.streams.push(value()?),
+                "--since" => args.since = Some(value()?),
+                "--until" => args.until = Some(value()?),
+                "--context" => args.context = Some(number(&arg, value()?)?),
+                "--limit" => args.limit = Some(number(&arg, value()?)?),
+                "--" => {
+                    args.positional.extend(raw);
+                    break;
+                }
+                flag if flag.starts_with('-') && flag.len() > 1 => {
+                    return Err(format!("Unknown option '{}'", flag));
+                }
+                _ => args.positional.push(arg),
+            }
+        }
+        Ok(args)
+    }
+
+    /// The positional argument after the command
+    fn target(&self, what: &str) -> Result<&str, String> {
+        self.positional
+            .get(1)
+            .map(String::as_str)
+            .ok_or_else(|| format!("Missing {}", what))
+    }
+}
+
+fn number(flag: &str, value: String) -> Result<usize, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} needs a number, not '{}'", flag, value))
+}
+
+/// A connection to the control socket of the app or `devbootd`
+struct Client {
+    reader: Box<dyn BufRead>,
+    writer: Box<dyn Write>,
+    next_id: u64,
+    /// Events read while waiting for a response
+    events: VecDeque<Value>,
+}
+
+impl Client {
+    fn connect(socket: &Path) -> Option<Self> {
+        let stream = crate::daemon::connect(socket).ok()?;
+        let writer = stream.try_clone().ok()?;
+        Some(Self {
+            reader: Box::new(std::io::BufReader::new(stream)),
+            writer: Box::new(writer),
+            next_id: 0,
+            events: VecDeque::new(),
+        })
+    }
+
+    fn read_message(&mut self) -> Result<Value, String> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => Err("DevBoot closed the connection".to_string()),
+            Ok(_) => serde_json::from_str(&line).map_err(|e| format!("Invalid message from DevBoot: {}", e)),
+            Err(e) => Err(format!("Failed to read from DevBoot: {}", e)),
+        }
+    }
+
+    /// Send a request and wait for its response
+    fn call(&mut self, method: &str, params: Value) -> Result<Value, String> {
+        self.next_id += 1;
+        let id = self.next_id;
+        let mut line = json!({ "id": id, "method": method, "params": params }).to_string();
+        line.push('\n');
+        self.writer
+            .write_all(line.as_bytes())
+            .map_err(|e| format!("Failed to send to DevBoot: {}", e))?;
+
+        loop {
+            let message = self.read_message()?;
+            if message.get("event").is_some() {
+                self.events.push_back(message);
+                continue;
+            }
+            if message["id"] != id {
+                continue;
+            }
+            if message["ok"] == true {
+                return Ok(message["result"].clone());
+            }
+            return Err(message["error"].as_str().unwrap_or("Request failed").to_string());
+        }
+    }
+
+    fn call_as<T: DeserializeOwned>(&mut self, method: &str, params: Value) -> Result<T, String> {
+        let result = self.call(method, params)?;
+        serde_json::from_value(result).map_err(|e| format!("Unexpected response from DevBoot: {}", e))
+    }
+
+    fn next_event(&mut self) -> Result<Value, String> {
+        if let Some(event) = self.events.pop_front() {
+            return Ok(event);
+        }
+        loop {
+            let message = self.read_message()?;
+            if message.get("event").is_some() {
+                return Ok(message);
+            }
+        }
+    }
+}
+
+/// Where projects are read from and commands are sent to
+enum Backend {
+    Running(Client),
+    /// Nothing is running; only `config.json` is available
+    Offline,
+}
+
+impl Backend {
+    fn projects(&mut self) -> Result<Vec<Project>, String> {
+        match self {
+            Backend::Running(client) => client.call_as("get_projects", Value::Null),
+            Backend::Offline => Ok(config::load_config().projects),
+        }
+    }
+
+    fn find(&mut self, query: &str) -> Result<Project, String> {
+        let projects = self.projects()?;
+        dependency::find_project(&projects, query)
+            .cloned()
+            .ok_or_else(|| format!("Project not found: {}", query))
+    }
+
+    /// Status of a project or one of its services; nothing runs while offline
+    fn status(&mut self, project_id: &str, service: Option<&str>) -> Result<String, String> {
+        match self {
+            Backend::Running(client) => client.call_as(
+                "get_project_status",
+                json!({ "project_id": project_id, "service": service }),
+            ),
+            Backend::Offline => Ok("stopped".to_string()),
+        }
+    }
+
+    fn client(&mut self) -> Result<&mut Client, String> {
+        match self {
+            Backend::Running(client) => Ok(client),
+            Backend::Offline => Err("DevBoot is not running; start the app or devbootd first".to_string()),
+        }
+    }
+}
+
+/// Run the CLI and return its exit code
+pub fn run(raw: Vec<String>) -> i32 {
+    let args = match Args::parse(raw) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("devboot-cli: {}\n\n{}", e, USAGE);
+            return 2;
+        }
+    };
+    let Some(command) = args.positional.first().cloned() else {
+        if args.help {
+            println!("{}", USAGE);
+            return 0;
+        }
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+    if args.help || command == "help" {
+        println!("{}", USAGE);
+        return 0;
+    }
+
+    match execute(&command, &args) {
+        Ok(()) => 0,
+        Err(e) => {
+            if args.json {
+                println!("{}", json!({ "error": e }));
+            } else {
+                eprintln!("devboot-cli: {}", e);
+            }
+            1
+        }
+    }
+}
+
+fn connect(args: &Args) -> Result<Backend, String> {
+    let default_socket = crate::daemon::socket_path();
+    match &args.socket {
+        // An explicit socket must answer
+        Some(socket) => Client::connect(socket)
+            .map(Backend::Running)
+            .ok_or_else(|| format!("Cannot connect to {}", socket.display())),
+        None => Ok(Client::connect(&default_socket).map_or(Backend::Offline, Backend::Running)),
+    }
+}
+
+fn execute(command: &str, args: &Args) -> Result<(), String> {
+    // Detection needs no backend
+    if command == "detect" {
+        return detect(args);
+    }
+
+    let mut backend = connect(args)?;
+    match command {
+        "list" => list(&mut backend, args),
+        "status" if args.positional.len() < 2 => list(&mut backend, args),
+        "status" => status(&mut backend, args),
+        "start" | "stop" | "restart" => lifecycle(&mut backend, args, command),
+        "logs" => logs(&mut backend, args),
+        "send" => send(&mut backend, args),
+        "search" => search(&mut backend, args),
+        "add" => add(&mut backend, args),
+        _ => Err(format!("Unknown command '{}'", command)),
+    }
+}
+
+/// Print `value` as JSON, or `text` otherwise
+fn output(args: &Args, value: Value, text: &str) {
+    if args.json {
+        println!("{}", value);
+    } else if !text.is_empty() {
+        println!("{}", text);
+    }
+}
+
+fn list(backend: &mut Backend, args: &Args) -> Result<(), String> {
+    let mut rows = Vec::new();
+    for project in backend.projects()? {
+        let status = backend.status(&project.id, None)?;
+        rows.push((project, status));
+    }
+
+    let value = Value::Array(
+        rows.iter()
+            .map(|(project, status)| json!({ "id": project.id, "name": project.name, "status": status }))
+            .collect(),
+    );
+    let width = rows.iter().map(|(p, _)| p.name.chars().count()).max().unwrap_or(0).max(4);
+    let mut text = format!("{:<width$}  {:<10}  ID", "NAME", "STATUS");
+    for (project, status) in &rows {
+        text.push_str(&format!("\n{:<width$}  {:<10}  {}", project.name, status, project.id));
+    }
+    output(args, value, &text);
+    Ok(())
+}
+
+fn status(backend: &mut Backend, args: &Args) -> Result<(), String> {
+    let project = backend.find(args.target("project")?)?;
+    if let Some(service) = &args.service {
+        let status = backend.status(&project.id, Some(service))?;
+        let value = json!({ "id": project.id, "name": project.name, "service": service, "status": status });
+        output(args, value, &format!("{}/{}: {}", project.name, service, status));
+        return Ok(());
+    }
+
+    let status = backend.status(&project.id, None)?;
+    let mut services = serde_json::Map::new();
+    let mut text = format!("{}: {}", project.name, status);
+    for service in &project.services {
+        let service_status = backend.status(&project.id, Some(&service.name))?;
+        text.push_str(&format!("\n  {}: {}", service.name, service_status));
+        services.insert(service.name.clone(), Value::String(service_status));
+    }
+    let value = json!({ "id": project.id, "name": project.name, "status": status, "services": services });
+    output(args, value, &text);
+    Ok(())
+}
+
+fn lifecycle(backend: &mut Backend, args: &Args, command: &str) -> Result<(), String> {
+    let project = backend.find(args.target("project")?)?;
+    let client = backend.client()?;
+    let label = match &args.service {
+        Some(service) => {
+            let method = format!("{}_service", command);
+            client.call(&method, json!({ "project_id": project.id, "service": service }))?;
+            format!("{}/{}", project.name, service)
+        }
+        None => {
+            let method = format!("{}_project", command);
+            client.call(&method, json!({ "project_id": project.id }))?;
+            project.name.clone()
+        }
+    };
+
+    let done = match command {
+        "start" => "Started",
+        "stop" => "Stopped",
+        _ => "Restarted",
+    };
+    output(args, json!({ "ok": true }), &format!("{} {}", done, label));
+    Ok(())
+}
+
+/// Services whose logs to show: the one asked for, every service of a
+/// project with services, or just the project
+fn log_units(project: &Project, service: Option<&String>) -> Vec<Option<String>> {
+    match service {
+        Some(service) => vec![Some(service.clone())],
+        None if project.services.is_empty() => vec![None],
+        None => project.services.iter().map(|s| Some(s.name.clone())).collect(),
+    }
+}
+
+fn logs(backend: &mut Backend, args: &Args) -> Result<(), String> {
+    let project = backend.find(args.target("project")?)?;
+    let units = log_units(&project, args.service.as_ref());
+    let prefix_services = args.service.is_none() && units.len() > 1;
+
+    // Without a running instance, show what the last session left on disk
+    if matches!(backend, Backend::Offline) && !args.follow {
+        let root = log_store::logs_dir();
+        let snapshot: Vec<(Option<String>, LogEntry)> = units
+            .iter()
+            .flat_map(|service| {
+                let unit_id = config::unit_id(&project.id, service.as_deref());
+                let entries = log_store::read_tail(&root, &unit_id, project.log_lines);
+                entries.into_iter().map(move |entry| (service.clone(), entry))
+            })
+            .collect();
+        print_snapshot(args, &project.id, &snapshot, prefix_services);
+        return Ok(());
+    }
+    let client = backend.client()?;
+
+    // Subscribe first so no line is lost between the snapshot and the stream
+    if args.follow {
+        client.call("subscribe", json!({ "events": ["process-log"] }))?;
+    }
+
+    let mut snapshot = Vec::new();
+    for service in &units {
+        let entries: Vec<LogEntry> = client.call_as(
+            "get_project_logs",
+            json!({ "project_id": project.id, "service": service }),
+        )?;
+        snapshot.extend(entries.into_iter().map(|entry| (service.clone(), entry)));
+    }
+    // Lines emitted before the snapshot are already part of it; later ones
+    // stay queued
+    let mut last_seq: HashMap<Option<String>, u64> = HashMap::new();
+    for (service, entry) in &snapshot {
+        let seq = last_seq.entry(service.clone()).or_default();
+        *seq = (*seq).max(entry.seq);
+    }
+
+    print_snapshot(args, &project.id, &snapshot, prefix_services);
+    if !args.follow {
+        return Ok(());
+    }
+
+    loop {
+        let event = client.next_event()?;
+        let payload = &event["payload"];
+        if payload["project_id"] != project.id.as_str() {
+            continue;
+        }
+        let service = payload["service"].as_str();
+        if !units.iter().any(|unit| unit.as_deref() == service) {
+            continue;
+        }
+        let Ok(entries) = serde_json::from_value::<Vec<LogEntry>>(payload["logs"].clone()) else {
+            continue;
+        };
+        let shown = last_seq.get(&service.map(str::to_string)).copied();
+        for entry in entries.iter().filter(|entry| shown.is_none_or(|seq| entry.seq > seq)) {
+            print_log(args, &project.id, service, entry, prefix_services);
+        }
+    }
+}
+
+fn print_snapshot(args: &Args, project_id: &str, snapshot: &[(Option<String>, LogEntry)], prefix_services: bool) {
+    if args.json && !args.follow {
+        let entries: Vec<&LogEntry> = snapshot.iter().map(|(_, entry)| entry).collect();
+        println!("{}", json!(entries));
+        return;
+    }
+    for (service, entry) in snapshot {
+        print_log(args, project_id, service.as_deref(), entry, prefix_services);
+    }
+}
+
+fn print_log(args: &Args, project_id: &str, service: Option<&str>, entry: &LogEntry, prefix_service: bool) {
+    if args.json {
+        println!("{}", json!({ "project_id": project_id, "service": service, "log": entry }));
+    } else if let (true, Some(service)) = (prefix_service, service) {
+        println!("[{}] {}", service, entry);
+    } else {
+        println!("{}", entry);
+    }
+    let _ = std::io::stdout().flush();
+}
+
+fn search(backend: &mut Backend, args: &Args) -> Result<(), String> {
+    let defaults = LogQuery::default();
+    let streams = args
+        .streams
+        .iter()
+        .map(|stream| serde_json::from_value(json!(stream)).map_err(|_| format!("Unknown stream '{}'", stream)))
+        .collect::<Result<_, _>>()?;
+    let query = LogQuery {
+        pattern: args.target("pattern")?.to_string(),
+        regex: args.regex,
+        case_sensitive: args.case_sensitive,
+        project_ids: args.projects.clone(),
+        streams,
+        since: args.since.clone(),
+        until: args.until.clone(),
+        context: args.context.unwrap_or(defaults.context),
+        limit: args.limit.unwrap_or(defaults.limit),
+    };
+
+    let projects = backend.projects()?;
+    let result: LogSearch = match backend {
+        Backend::Running(client) => client.call_as("search_logs", json!({ "query": query }))?,
+        // Without a running instance, search what the log files hold
+        Backend::Offline => {
+            let search = Search::new(query)?;
+            let root = log_store::logs_dir();
+            let units = search
+                .unit_ids(&projects)?
+                .into_iter()
+                .map(|unit_id| {
+                    let entries = log_store::read_all(&root, &unit_id, search.since());
+                    (unit_id, entries)
+                })
+                .collect();
+            search.run(units)
+        }
+    };
+    if args.json {
+        println!("{}", json!(result));
+        return Ok(());
+    }
+
+    // `name: line` for matches and `name- line` around them, as grep does
+    for (index, found) in result.matches.iter().enumerate() {
+        let name = projects
+            .iter()
+            .find(|project| project.id == found.unit.project_id)
+            .map_or(found.unit.project_id.as_str(), |project| project.name.as_str());
+        let label = match &found.unit.service {
+            Some(service) => format!("{}/{}", name, service),
+            None => name.to_string(),
+        };
+        if index > 0 && (!found.before.is_empty() || !found.after.is_empty()) {
+            println!("--");
+        }
+        for entry in &found.before {
+            println!("{}- {}", label, entry);
+        }
+        println!("{}: {}", label, found.entry);
+        for entry in &found.after {
+            println!("{}- {}", label, entry);
+        }
+    }
+    if result.matches.is_empty() {
+        println!("No matching lines");
+    } else if result.truncated {
+        println!("Only the newest {} matches are shown; see --limit", result.matches.len());
+    }
+    Ok(())
+}
+
+fn send(backend: &mut Backend, args: &Args) -> Result<(), String> {
+    let project = backend.find(args.target("project")?)?;
+    let input = args.positional.get(2..).unwrap_or_default().join(" ");
+    if input.is_empty() {
+        return Err("Missing input".to_string());
+    }
+    backend.client()?.call(
+        "send_project_input",
+        json!({ "project_id": project.id, "service": args.service, "input": input }),
+    )?;
+    output(args, json!({ "ok": true }), "");
+    Ok(())
+}
+
+/// Absolute form of a path given on the command line, when it exists
+fn absolute_path(path: &str) -> String {
+    std::fs::canonicalize(path)
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+fn add(backend: &mut Backend, args: &Args) -> Result<(), String> {
+    let path = absolute_path(args.target("path")?);
+    if !Path::new(&path).is_dir() {
+        return Err(format!("Project path does not exist: {}", path));
+    }
+
+    // Fill in what was not given from detection
+    let detected = detector::detect_project(&path);
+    let name = args.name.clone().unwrap_or_else(|| detected.name.clone());
+    let commands = if args.commands.is_empty() {
+        let suggestion = detected
+            .suggestions
+            .iter()
+            .find(|s| s.is_recommended)
+            .ok_or_else(|| format!("No command detected for {}; pass --command", path))?;
+        vec![suggestion.command.clone()]
+    } else {
+        args.commands.clone()
+    };
+
+    let project = match backend {
+        Backend::Running(client) => client.call_as(
+            "add_project",
+            json!({ "name": name, "path": path, "commands": commands }),
+        )?,
+        Backend::Offline => {
+            let project = Project::new(name, path, commands);
+            let mut config = config::load_config();
+            config.projects.push(project.clone());
+            config::save_config(&config)?;
+            project
+        }
+    };
+
+    let text = format!("Added {} ({})\n  $ {}", project.name, project.id, project.commands.join("\n  $ "));
+    output(args, serde_json::to_value(&project).map_err(|e| e.to_string())?, &text);
+    Ok(())
+}
+
+fn detect(args: &Args) -> Result<(), String> {
+    let path = absolute_path(args.target("path")?);
+    let detected: DetectedProject = detector::detect_project(&path);
+
+    let mut text = match &detected.framework {
+        Some(framework) => format!("{}: {} ({})", detected.name, detected.project_type, framework),
+        None => format!("{}: {}", detected.name, detected.project_type),
+    };
+    for suggestion in &detected.suggestions {
+        let mark = if suggestion.is_recommended { "*" } else { " " };
+        text.push_str(&format!("\n{} {}  # {}", mark, suggestion.command, suggestion.description));
+    }
+    output(args, serde_json::to_value(&detected).map_err(|e| e.to_string())?, &text);
+    Ok(())
+}
@@ -1,15 +1,27 @@
 //! Tauri IPC commands for DevBoot
-//! These commands are called from the frontend
+//! These commands are called from the frontend. Each one forwards to the
+//! `AppState` method of the same name, which the control socket of
+//! `devbootd` calls as well.
 
 use crate::config::{self, AppConfig, Project, Settings};
-use crate::process_manager::{ProcessManager, ProcessStatus};
+use crate::dependency;
+use crate::log_search::{LogQuery, LogSearch, Search};
+use crate::process_manager::{BreakerPayload, LogDirection, LogEntry, LogPage, ProcessManager, ProcessStatus};
+use crate::pty::ControlKey;
+use crate::resources::ResourceSample;
 use crate::startup;
-use std::sync::Mutex;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tauri::State;
 
-/// Application state
+/// Pause between stopping and starting again on restart
+const RESTART_DELAY: Duration = Duration::from_millis(500);
+
+/// Application state. Clones share the same config and processes.
+#[derive(Clone)]
 pub struct AppState {
-    pub config: Mutex<AppConfig>,
+    pub config: Arc<Mutex<AppConfig>>,
     pub process_manager: ProcessManager,
 }
 
@@ -17,10 +29,245 @@ impl AppState {
     pub fn new() -> Self {
         let config = config::load_config();
         Self {
-            config: Mutex::new(config),
+            config: Arc::new(Mutex::new(config)),
             process_manager: ProcessManager::new(),
         }
     }
+
+    /// Apply a change to a copy of the config and only keep it if it validates
+    /// and saves successfully
+    fn update_config<F: FnOnce(&mut AppConfig)>(&self, change: F) -> Result<(), String> {
+        let mut config = self.config.lock().unwrap();
+        let mut candidate = config.clone();
+        change(&mut candidate);
+        config::save_config(&candidate)?;
+        *config = candidate;
+        Ok(())
+    }
+
+    // ============ Config ============
+
+    pub fn get_config(&self) -> AppConfig {
+        self.config.lock().unwrap().clone()
+    }
+
+    pub fn save_config(&self, config: AppConfig) -> Result<(), String> {
+        let settings = config.settings.clone();
+        self.update_config(|current| *current = config)?;
+        self.process_manager.set_default_shell(settings.default_shell);
+        self.process_manager.set_log_settings(settings.logs);
+        Ok(())
+    }
+
+    pub fn get_projects(&self) -> Vec<Project> {
+        self.config.lock().unwrap().projects.clone()
+    }
+
+    pub fn add_project(&self, name: String, path: String, commands: Vec<String>) -> Result<Project, String> {
+        let project = Project::new(name, path, commands);
+        self.update_config(|config| config.projects.push(project.clone()))?;
+        Ok(project)
+    }
+
+    pub fn update_project(&self, project: Project) -> Result<(), String> {
+        self.update_config(|config| {
+            if let Some(p) = config.projects.iter_mut().find(|p| p.id == project.id) {
+                *p = project;
+            }
+        })
+    }
+
+    pub async fn delete_project(&self, project_id: String) -> Result<(), String> {
+        // Fails if other projects still depend on this one
+        self.update_config(|config| config.projects.retain(|p| p.id != project_id))?;
+
+        // Stop the project if running
+        self.process_manager.stop(&project_id).await.ok();
+        Ok(())
+    }
+
+    pub fn get_settings(&self) -> Settings {
+        self.config.lock().unwrap().settings.clone()
+    }
+
+    pub fn update_settings(&self, settings: Settings) -> Result<(), String> {
+        self.update_config(|config| config.settings = settings.clone())?;
+        self.process_manager.set_default_shell(settings.default_shell);
+        self.process_manager.set_log_settings(settings.logs);
+        Ok(())
+    }
+
+    // ============ Process ============
+
+    pub fn start_project(&self, project_id: String) -> Result<(), String> {
+        let config = self.config.lock().unwrap();
+        let mut plan = dependency::start_order(&config.projects, &[project_id.as_str()])?;
+        drop(config);
+
+        // Without dependencies, start directly so errors reach the caller
+        if plan.len() == 1 {
+            let project = plan.remove(0);
+            return self.process_manager.start_project(&project);
+        }
+
+        if self.process_manager.is_running(&project_id) {
+            return Err("Project is already running".to_string());
+        }
+        self.process_manager.start_in_order(plan);
+        Ok(())
+    }
+
+    /// Stop a project once it and its dependents have exited
+    pub async fn stop_project(&self, project_id: String) -> Result<(), String> {
+        // Stop projects that depend on this one first
+        let order = {
+            let config = self.config.lock().unwrap();
+            dependency::stop_order(&config.projects, &[project_id.as_str()])
+        };
+
+        for id in order {
+            if id == project_id || self.process_manager.get_status(&id) != ProcessStatus::Stopped {
+                self.process_manager.stop(&id).await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn restart_project(&self, project_id: String) -> Result<(), String> {
+        // Only this project restarts; its dependents keep running
+        self.process_manager.stop(&project_id).await?;
+
+        // Small delay before restart
+        tokio::time::sleep(RESTART_DELAY).await;
+
+        self.start_project(project_id)
+    }
+
+    pub fn start_service(&self, project_id: String, service: String) -> Result<(), String> {
+        let config = self.config.lock().unwrap();
+        let project = dependency::find_project(&config.projects, &project_id)
+            .cloned()
+            .ok_or_else(|| format!("Project not found: {}", project_id))?;
+        drop(config);
+        self.process_manager.start_service(&project, &service)
+    }
+
+    pub async fn stop_service(&self, project_id: String, service: String) -> Result<(), String> {
+        // The project's other services and its dependents keep running
+        self.process_manager.stop(&config::unit_id(&project_id, Some(&service))).await
+    }
+
+    pub async fn restart_service(&self, project_id: String, service: String) -> Result<(), String> {
+        self.process_manager.stop(&config::unit_id(&project_id, Some(&service))).await?;
+
+        // Small delay before restart
+        tokio::time::sleep(RESTART_DELAY).await;
+
+        self.start_service(project_id, service)
+    }
+
+    /// Status of each configured service of a project, by name
+    pub fn get_service_statuses(&self, project_id: String) -> HashMap<String, String> {
+        let config = self.config.lock().unwrap();
+        let Some(project) = dependency::find_project(&config.projects, &project_id) else {
+            return HashMap::new();
+        };
+        project
+            .services
+            .iter()
+            .map(|service| {
+                let status = self
+                    .process_manager
+                    .get_status(&config::unit_id(&project.id, Some(&service.name)));
+                (service.name.clone(), status.as_str().to_string())
+            })
+            .collect()
+    }
+
+    pub fn get_project_status(&self, project_id: String, service: Option<String>) -> String {
+        let unit_id = config::unit_id(&project_id, service.as_deref());
+        self.process_manager.get_status(&unit_id).as_str().to_string()
+    }
+
+    pub fn get_project_logs(&self, project_id: String, service: Option<String>) -> Vec<LogEntry> {
+        self.process_manager.get_logs(&config::unit_id(&project_id, service.as_deref()))
+    }
+
+    /// Matching log lines of the selected projects, with context.
+    /// Reading the log files can take a while, so it runs on a blocking thread.
+    pub async fn search_logs(&self, query: LogQuery) -> Result<LogSearch, String> {
+        let state = self.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            let search = Search::new(query)?;
+            let unit_ids = search.unit_ids(&state.config.lock().unwrap().projects)?;
+            Ok(state.process_manager.search_logs(unit_ids, &search))
+        })
+        .await
+        .map_err(|e| format!("Search failed: {}", e))?
+    }
+
+    /// Up to `limit` log entries before (the default) or after `cursor`
+    pub fn get_log_page(
+        &self,
+        project_id: String,
+        service: Option<String>,
+        cursor: Option<u64>,
+        direction: Option<LogDirection>,
+        limit: usize,
+    ) -> LogPage {
+        let unit_id = config::unit_id(&project_id, service.as_deref());
+        self.process_manager
+            .get_log_page(&unit_id, cursor, direction.unwrap_or_default(), limit)
+    }
+
+    pub fn get_project_stats(&self, project_id: String, service: Option<String>) -> Vec<ResourceSample> {
+        self.process_manager.get_stats(&config::unit_id(&project_id, service.as_deref()))
+    }
+
+    pub fn get_breaker_status(&self, project_id: String, service: Option<String>) -> BreakerPayload {
+        self.process_manager.get_breaker_status(&config::unit_id(&project_id, service.as_deref()))
+    }
+
+    pub fn clear_project_logs(&self, project_id: String, service: Option<String>) {
+        self.process_manager.clear_logs(&config::unit_id(&project_id, service.as_deref()));
+    }
+
+    /// Writing to a pipe blocks until the project reads it, so input is sent
+    /// off the calling thread
+    pub async fn send_project_input(&self, project_id: String, service: Option<String>, input: String) -> Result<(), String> {
+        self.blocking(move |manager| manager.send_input(&config::unit_id(&project_id, service.as_deref()), &input))
+            .await
+    }
+
+    pub async fn send_project_interrupt(&self, project_id: String, service: Option<String>) -> Result<(), String> {
+        self.blocking(move |manager| manager.send_interrupt(&config::unit_id(&project_id, service.as_deref())))
+            .await
+    }
+
+    pub async fn send_project_control(&self, project_id: String, service: Option<String>, key: ControlKey) -> Result<(), String> {
+        self.blocking(move |manager| manager.send_control(&config::unit_id(&project_id, service.as_deref()), key))
+            .await
+    }
+
+    pub fn resize_project_terminal(&self, project_id: String, service: Option<String>, cols: u16, rows: u16) -> Result<(), String> {
+        self.process_manager.resize_terminal(&config::unit_id(&project_id, service.as_deref()), cols, rows)
+    }
+
+    pub async fn stop_all_projects(&self) {
+        self.process_manager.stop_all().await;
+    }
+
+    /// Run `f` on the blocking thread pool
+    async fn blocking<T, F>(&self, f: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce(&ProcessManager) -> Result<T, String> + Send + 'static,
+    {
+        let manager = self.process_manager.clone();
+        tauri::async_runtime::spawn_blocking(move || f(&manager))
+            .await
+            .map_err(|e| format!("Task failed: {}", e))?
+    }
 }
 
 impl Default for AppState {
@@ -33,19 +280,17 @@ impl Default for AppState {
 
 #[tauri::command]
 pub fn get_config(state: State<AppState>) -> AppConfig {
-    state.config.lock().unwrap().clone()
+    state.get_config()
 }
 
 #[tauri::command]
 pub fn save_config_cmd(state: State<AppState>, config: AppConfig) -> Result<(), String> {
-    let mut current = state.config.lock().unwrap();
-    *current = config.clone();
-    config::save_config(&config)
+    state.save_config(config)
 }
 
 #[tauri::command]
 pub fn get_projects(state: State<AppState>) -> Vec<Project> {
-    state.config.lock().unwrap().projects.clone()
+    state.get_projects()
 }
 
 #[tauri::command]
@@ -55,113 +300,134 @@ pub fn add_project(
     path: String,
     commands: Vec<String>,
 ) -> Result<Project, String> {
-    let project = Project::new(name, path, commands);
-    let mut config = state.config.lock().unwrap();
-    config.projects.push(project.clone());
-    config::save_config(&config)?;
-    Ok(project)
+    state.add_project(name, path, commands)
 }
 
 #[tauri::command]
 pub fn update_project(state: State<AppState>, project: Project) -> Result<(), String> {
-    let mut config = state.config.lock().unwrap();
-    if let Some(p) = config.projects.iter_mut().find(|p| p.id == project.id) {
-        *p = project;
-    }
-    config::save_config(&config)
+    state.update_project(project)
 }
 
 #[tauri::command]
-pub fn delete_project(state: State<AppState>, project_id: String) -> Result<(), String> {
-    // Stop the project first if running
-    state.process_manager.stop_project(&project_id).ok();
-    
-    let mut config = state.config.lock().unwrap();
-    config.projects.retain(|p| p.id != project_id);
-    config::save_config(&config)
+pub async fn delete_project(state: State<'_, AppState>, project_id: String) -> Result<(), String> {
+    state.delete_project(project_id).await
 }
 
 #[tauri::command]
 pub fn get_settings(state: State<AppState>) -> Settings {
-    state.config.lock().unwrap().settings.clone()
+    state.get_settings()
 }
 
 #[tauri::command]
 pub fn update_settings(state: State<AppState>, settings: Settings) -> Result<(), String> {
-    let mut config = state.config.lock().unwrap();
-    config.settings = settings;
-    config::save_config(&config)
+    state.update_settings(settings)
 }
 
 // ============ Process Commands ============
 
 #[tauri::command]
 pub fn start_project(state: State<AppState>, project_id: String) -> Result<(), String> {
-    let config = state.config.lock().unwrap();
-    let project = config
-        .projects
-        .iter()
-        .find(|p| p.id == project_id)
-        .ok_or("Project not found")?
-        .clone();
-    drop(config);
+    state.start_project(project_id)
+}
+
+// Stopping waits for the process to exit, so these run off the main thread
 
-    state.process_manager.start_project(
-        &project.id,
-        &project.path,
-        &project.commands,
-        project.restart_on_crash,
-    )
+#[tauri::command]
+pub async fn stop_project(state: State<'_, AppState>, project_id: String) -> Result<(), String> {
+    state.stop_project(project_id).await
 }
 
 #[tauri::command]
-pub fn stop_project(state: State<AppState>, project_id: String) -> Result<(), String> {
-    state.process_manager.stop_project(&project_id)
+pub async fn restart_project(state: State<'_, AppState>, project_id: String) -> Result<(), String> {
+    state.restart_project(project_id).await
 }
 
 #[tauri::command]
-pub fn restart_project(state: State<AppState>, project_id: String) -> Result<(), String> {
-    state.process_manager.stop_project(&project_id)?;
-    
-    // Small delay before restart
-    std::thread::sleep(std::time::Duration::from_millis(500));
-    
-    start_project(state, project_id)
+pub fn start_service(state: State<AppState>, project_id: String, service: String) -> Result<(), String> {
+    state.start_service(project_id, service)
 }
 
 #[tauri::command]
-pub fn get_project_status(state: State<AppState>, project_id: String) -> String {
-    match state.process_manager.get_status(&project_id) {
-        ProcessStatus::Stopped => "stopped".to_string(),
-        ProcessStatus::Running => "running".to_string(),
-        ProcessStatus::Error => "error".to_string(),
-        ProcessStatus::Restarting => "restarting".to_string(),
-    }
+pub async fn stop_service(state: State<'_, AppState>, project_id: String, service: String) -> Result<(), String> {
+    state.stop_service(project_id, service).await
+}
+
+#[tauri::command]
+pub async fn restart_service(state: State<'_, AppState>, project_id: String, service: String) -> Result<(), String> {
+    state.restart_service(project_id, service).await
+}
+
+#[tauri::command]
+pub fn get_service_statuses(state: State<AppState>, project_id: String) -> HashMap<String, String> {
+    state.get_service_statuses(project_id)
+}
+
+#[tauri::command]
+pub fn get_project_status(state: State<AppState>, project_id: String, service: Option<String>) -> String {
+    state.get_project_status(project_id, service)
+}
+
+#[tauri::command]
+pub fn get_project_logs(state: State<AppState>, project_id: String, service: Option<String>) -> Vec<LogEntry> {
+    state.get_project_logs(project_id, service)
+}
+
+#[tauri::command]
+pub async fn search_logs(state: State<'_, AppState>, query: LogQuery) -> Result<LogSearch, String> {
+    state.search_logs(query).await
+}
+
+#[tauri::command]
+pub fn get_log_page(
+    state: State<AppState>,
+    project_id: String,
+    service: Option<String>,
+    cursor: Option<u64>,
+    direction: Option<LogDirection>,
+    limit: usize,
+) -> LogPage {
+    state.get_log_page(project_id, service, cursor, direction, limit)
+}
+
+#[tauri::command]
+pub fn get_project_stats(state: State<AppState>, project_id: String, service: Option<String>) -> Vec<ResourceSample> {
+    state.get_project_stats(project_id, service)
+}
+
+#[tauri::command]
+pub fn get_breaker_status(state: State<AppState>, project_id: String, service: Option<String>) -> BreakerPayload {
+    state.get_breaker_status(project_id, service)
+}
+
+#[tauri::command]
+pub fn clear_project_logs(state: State<AppState>, project_id: String, service: Option<String>) {
+    state.clear_project_logs(project_id, service);
 }
 
 #[tauri::command]
-pub fn get_project_logs(state: State<AppState>, project_id: String) -> Vec<String> {
-    state.process_manager.get_logs(&project_id)
+pub async fn send_project_input(state: State<'_, AppState>, project_id: String, service: Option<String>, input: String) -> Result<(), String> {
+    state.send_project_input(project_id, service, input).await
 }
 
 #[tauri::command]
-pub fn clear_project_logs(state: State<AppState>, project_id: String) {
-    state.process_manager.clear_logs(&project_id);
+pub async fn send_project_interrupt(state: State<'_, AppState>, project_id: String, service: Option<String>) -> Result<(), String> {
+    state.send_project_interrupt(project_id, service).await
 }
 
 #[tauri::command]
-pub fn send_project_input(state: State<AppState>, project_id: String, input: String) -> Result<(), String> {
-    state.process_manager.send_input(&project_id, &input)
+pub async fn send_project_control(state: State<'_, AppState>, project_id: String, service: Option<String>, key: ControlKey) -> Result<(), String> {
+    state.send_project_control(project_id, service, key).await
 }
 
 #[tauri::command]
-pub fn send_project_interrupt(state: State<AppState>, project_id: String) -> Result<(), String> {
-    state.process_manager.send_interrupt(&project_id)
+pub fn resize_project_terminal(state: State<AppState>, project_id: String, service: Option<String>, cols: u16, rows: u16) -> Result<(), String> {
+    state.resize_project_terminal(project_id, service, cols, rows)
 }
 
 #[tauri::command]
-pub fn stop_all_projects(state: State<AppState>) {
-    state.process_manager.stop_all();
+pub async fn stop_all_projects(state: State<'_, AppState>) -> Result<(), String> {
+    state.stop_all_projects().await;
+    Ok(())
 }
 
 // ============ Startup Commands ============
@@ -2,6 +2,497 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
 
+use crate::cron::CronExpr;
+use crate::dependency;
+use crate::watcher;
+
+/// Shell used to run a project's commands
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ShellProfile {
+    #[default]
+    Bash,
+    Sh,
+    Zsh,
+    Fish,
+    Pwsh,
+    /// Run a single command directly, without any shell
+    Direct,
+}
+
+/// What a health probe checks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProbeCheck {
+    /// `GET` an `http://` URL and expect a status code
+    Http {
+        url: String,
+        #[serde(default = "default_expected_status")]
+        expected_status: u16,
+    },
+    /// Open a TCP connection
+    Tcp {
+        #[serde(default = "default_probe_host")]
+        host: String,
+        port: u16,
+    },
+    /// A log line matching the regex was printed since the last check
+    LogPattern { pattern: String },
+    /// Run a command with the project's shell; exit code 0 is healthy
+    Command { command: String },
+}
+
+fn default_expected_status() -> u16 {
+    200
+}
+
+fn default_probe_host() -> String {
+    "127.0.0.1".to_string()
+}
+
+/// A health probe and its timing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Probe {
+    #[serde(flatten)]
+    pub check: ProbeCheck,
+    #[serde(default = "default_probe_interval_ms")]
+    pub interval_ms: u64,
+    #[serde(default = "default_probe_timeout_ms")]
+    pub timeout_ms: u64,
+    #[serde(default)]
+    pub initial_delay_ms: u64,
+    /// Consecutive liveness failures before the project is restarted
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+}
+
+fn default_probe_interval_ms() -> u64 {
+    5000
+}
+
+fn default_probe_timeout_ms() -> u64 {
+    2000
+}
+
+fn default_failure_threshold() -> u32 {
+    3
+}
+
+/// Readiness and liveness probes for a project
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HealthChecks {
+    /// Must pass before the project is considered healthy
+    #[serde(default)]
+    pub readiness: Option<Probe>,
+    /// Checked for the whole run; repeated failures restart the project
+    #[serde(default)]
+    pub liveness: Option<Probe>,
+}
+
+impl HealthChecks {
+    pub fn is_empty(&self) -> bool {
+        self.readiness.is_none() && self.liveness.is_none()
+    }
+}
+
+/// When a project is restarted after its process exits
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RestartMode {
+    Never,
+    /// Restart after a crash (see `ExitCodes`) or a failed liveness probe
+    #[default]
+    OnFailure,
+    /// Restart after any exit that was not requested by the user
+    Always,
+}
+
+/// Restart behaviour with exponential backoff
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestartPolicy {
+    #[serde(default)]
+    pub mode: RestartMode,
+    /// Attempts before giving up; `null` retries forever
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: Option<u32>,
+    #[serde(default = "default_initial_delay_ms")]
+    pub initial_delay_ms: u64,
+    /// Factor applied to the delay after each consecutive attempt
+    #[serde(default = "default_backoff_multiplier")]
+    pub multiplier: f64,
+    #[serde(default = "default_max_delay_ms")]
+    pub max_delay_ms: u64,
+    /// Uptime after which a run counts as stable and the attempt counter resets
+    #[serde(default = "default_reset_after_secs")]
+    pub reset_after_secs: u64,
+    /// Back off and retry later instead of giving up for good
+    #[serde(default)]
+    pub circuit_breaker: Option<CircuitBreaker>,
+}
+
+/// Pauses a crash-looping project. It trips after `max_crashes` crashes
+/// within `window_secs`, or when `max_attempts` runs out, and tries again
+/// after `cool_off_secs` with a fresh attempt counter.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitBreaker {
+    #[serde(default = "default_breaker_max_crashes")]
+    pub max_crashes: u32,
+    #[serde(default = "default_breaker_window_secs")]
+    pub window_secs: u64,
+    #[serde(default = "default_breaker_cool_off_secs")]
+    pub cool_off_secs: u64,
+}
+
+fn default_breaker_max_crashes() -> u32 {
+    5
+}
+
+fn default_breaker_window_secs() -> u64 {
+    60
+}
+
+fn default_breaker_cool_off_secs() -> u64 {
+    300
+}
+
+fn default_max_attempts() -> Option<u32> {
+    Some(5)
+}
+
+fn default_initial_delay_ms() -> u64 {
+    2000
+}
+
+fn default_backoff_multiplier() -> f64 {
+    2.0
+}
+
+fn default_max_delay_ms() -> u64 {
+    60_000
+}
+
+fn default_reset_after_secs() -> u64 {
+    300
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            mode: RestartMode::default(),
+            max_attempts: default_max_attempts(),
+            initial_delay_ms: default_initial_delay_ms(),
+            multiplier: default_backoff_multiplier(),
+            max_delay_ms: default_max_delay_ms(),
+            reset_after_secs: default_reset_after_secs(),
+            circuit_breaker: None,
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// Delay before the given 1-based restart attempt
+    pub fn delay_for(&self, attempt: u32) -> std::time::Duration {
+        let exponent = attempt.saturating_sub(1).min(64) as i32;
+        let delay = self.initial_delay_ms as f64 * self.multiplier.max(1.0).powi(exponent);
+        std::time::Duration::from_millis(delay.min(self.max_delay_ms as f64) as u64)
+    }
+
+    /// Whether the given 1-based attempt is within `max_attempts`
+    pub fn allows_attempt(&self, attempt: u32) -> bool {
+        self.max_attempts.is_none_or(|max| attempt <= max)
+    }
+}
+
+/// How the main process's exit code is interpreted. A process killed by a
+/// signal has no exit code and always counts as crashed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExitCodes {
+    /// Codes that count as a normal exit rather than a crash
+    #[serde(default = "default_success_codes")]
+    pub success: Vec<i32>,
+    /// Codes after which the process is never restarted, whatever the
+    /// restart mode
+    #[serde(default)]
+    pub no_restart: Vec<i32>,
+}
+
+fn default_success_codes() -> Vec<i32> {
+    vec![0]
+}
+
+impl Default for ExitCodes {
+    fn default() -> Self {
+        Self {
+            success: default_success_codes(),
+            no_restart: Vec::new(),
+        }
+    }
+}
+
+impl ExitCodes {
+    pub fn is_success(&self, code: Option<i32>) -> bool {
+        code.is_some_and(|code| self.success.contains(&code))
+    }
+
+    pub fn prevents_restart(&self, code: Option<i32>) -> bool {
+        code.is_some_and(|code| self.no_restart.contains(&code))
+    }
+}
+
+/// Signal sent to a project's process group when it is stopped
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum StopSignal {
+    #[default]
+    #[serde(rename = "SIGTERM")]
+    Term,
+    #[serde(rename = "SIGINT")]
+    Int,
+    #[serde(rename = "SIGHUP")]
+    Hup,
+    #[serde(rename = "SIGQUIT")]
+    Quit,
+    #[serde(rename = "SIGKILL")]
+    Kill,
+}
+
+fn default_stop_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_log_lines() -> usize {
+    1000
+}
+
+fn default_max_log_rate() -> u32 {
+    1000
+}
+
+/// What happens to scheduled runs missed while DevBoot was closed
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CatchUp {
+    #[default]
+    Skip,
+    /// Run once at startup if any run was missed
+    RunOnce,
+}
+
+/// Cron-driven starts, restarts and active hours for a project.
+/// Expressions use the five-field cron syntax in local time.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Schedule {
+    /// Start the project, e.g. a job that runs to completion
+    #[serde(default)]
+    pub run: Option<String>,
+    /// Restart the project if it is running, e.g. to recycle a service daily
+    #[serde(default)]
+    pub restart: Option<String>,
+    /// Minutes during which the project may run; it is started when the
+    /// window opens and stopped outside it
+    #[serde(default)]
+    pub active_during: Option<String>,
+    /// Applies to `run` times missed while DevBoot was closed
+    #[serde(default)]
+    pub catch_up: CatchUp,
+}
+
+impl Schedule {
+    /// Check that every expression parses
+    pub fn validate(&self) -> Result<(), String> {
+        let fields = [
+            ("run", &self.run),
+            ("restart", &self.restart),
+            ("active_during", &self.active_during),
+        ];
+        for (name, expr) in fields {
+            if let Some(expr) = expr {
+                CronExpr::parse(expr).map_err(|e| format!("{} '{}': {}", name, expr, e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What the watchdog does when a project exceeds one of its limits
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitAction {
+    /// Only report the violation, once per run
+    Log,
+    /// Stop the project gracefully and start it again
+    Restart,
+    #[default]
+    Stop,
+}
+
+/// Resource limits for a project; unset limits are not enforced
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResourceLimits {
+    /// Ceiling on the resident memory of the whole process tree (Linux only)
+    #[serde(default)]
+    pub max_memory_mb: Option<u64>,
+    /// `RLIMIT_NOFILE` set on the process at spawn (Unix only)
+    #[serde(default)]
+    pub max_open_files: Option<u64>,
+    /// Niceness set at spawn, from -20 to 19 (Unix only)
+    #[serde(default)]
+    pub nice: Option<i32>,
+    /// Longest a single run may last
+    #[serde(default)]
+    pub max_runtime_secs: Option<u64>,
+    /// Applied when the memory or runtime limit is exceeded
+    #[serde(default)]
+    pub action: LimitAction,
+}
+
+/// Restart a running project when files under its path change
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WatchConfig {
+    /// Globs relative to the project path; empty watches every file
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Globs relative to the project path that never trigger a restart
+    #[serde(default = "default_watch_exclude")]
+    pub exclude: Vec<String>,
+    /// Quiet period after the last change before restarting
+    #[serde(default = "default_watch_debounce_ms")]
+    pub debounce_ms: u64,
+}
+
+fn default_watch_exclude() -> Vec<String> {
+    ["**/.git/**", "**/node_modules/**", "**/__pycache__/**"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn default_watch_debounce_ms() -> u64 {
+    500
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: default_watch_exclude(),
+            debounce_ms: default_watch_debounce_ms(),
+        }
+    }
+}
+
+/// Commands run at points of a project's lifecycle, through the project's
+/// shell and with `DEVBOOT_*` variables describing the event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hooks {
+    /// Before each start of `commands`, restarts included
+    #[serde(default)]
+    pub before_start: Option<String>,
+    /// Once `commands` are spawned; runs alongside them
+    #[serde(default)]
+    pub after_start: Option<String>,
+    /// After every crash, whether or not a restart follows
+    #[serde(default)]
+    pub on_crash: Option<String>,
+    /// When the restart policy runs out of attempts
+    #[serde(default)]
+    pub on_give_up: Option<String>,
+    /// After the project was stopped or exited normally
+    #[serde(default)]
+    pub after_stop: Option<String>,
+    /// A hook still running after this long is killed
+    #[serde(default = "default_hook_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_hook_timeout_ms() -> u64 {
+    30000
+}
+
+impl Default for Hooks {
+    fn default() -> Self {
+        Self {
+            before_start: None,
+            after_start: None,
+            on_crash: None,
+            on_give_up: None,
+            after_stop: None,
+            timeout_ms: default_hook_timeout_ms(),
+        }
+    }
+}
+
+/// Initial size of a project's pseudo-terminal
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PtyOptions {
+    #[serde(default = "default_pty_cols")]
+    pub cols: u16,
+    #[serde(default = "default_pty_rows")]
+    pub rows: u16,
+}
+
+fn default_pty_cols() -> u16 {
+    120
+}
+
+fn default_pty_rows() -> u16 {
+    30
+}
+
+impl Default for PtyOptions {
+    fn default() -> Self {
+        Self {
+            cols: default_pty_cols(),
+            rows: default_pty_rows(),
+        }
+    }
+}
+
+/// A named long-running command of a project, supervised on its own.
+/// It shares the project's path, env files, shell and stop settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Service {
+    pub name: String,
+    pub commands: Vec<String>,
+    /// Applied on top of the project's `env_vars`
+    #[serde(default)]
+    pub env_vars: HashMap<String, String>,
+    /// Overrides the project's `restart_on_crash`
+    #[serde(default)]
+    pub restart_on_crash: Option<bool>,
+    /// Overrides the project's `restart_policy`
+    #[serde(default)]
+    pub restart_policy: Option<RestartPolicy>,
+    /// Overrides the project's `exit_codes`
+    #[serde(default)]
+    pub exit_codes: Option<ExitCodes>,
+    /// Probes for this service; the project's `health` does not apply
+    #[serde(default)]
+    pub health: HealthChecks,
+    /// Used instead of the project's `setup`
+    #[serde(default)]
+    pub setup: Vec<String>,
+    /// Used instead of the project's `cleanup`
+    #[serde(default)]
+    pub cleanup: Vec<String>,
+}
+
+/// Id of a separately supervised unit: the project id for a project
+/// without services, `<project id>/<service>` for a service
+pub fn unit_id(project_id: &str, service: Option<&str>) -> String {
+    match service {
+        Some(service) => format!("{}/{}", project_id, service),
+        None => project_id.to_string(),
+    }
+}
+
+/// Split a unit id into the project id and service name
+pub fn split_unit_id(id: &str) -> (&str, Option<&str>) {
+    match id.rsplit_once('/') {
+        Some((project_id, service)) => (project_id, Some(service)),
+        None => (id, None),
+    }
+}
+
 /// Project configuration for a single project
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Project {
@@ -12,11 +503,75 @@ pub struct Project {
     pub auto_start: bool,
     pub restart_on_crash: bool,
     pub enabled: bool,
+    /// Steps run one by one before `commands` on every start; any failure
+    /// stops the start
+    #[serde(default)]
+    pub setup: Vec<String>,
+    /// Also run `setup` before crash restarts
+    #[serde(default)]
+    pub setup_on_restart: bool,
+    /// Steps run after the project stops, unless it is about to restart
+    #[serde(default)]
+    pub cleanup: Vec<String>,
+    /// Shared by all services, which pass their name as `DEVBOOT_SERVICE`
+    #[serde(default)]
+    pub hooks: Hooks,
     #[serde(default)]
     pub env_vars: HashMap<String, String>,
+    /// Dotenv-style files loaded in order before `env_vars` are applied
+    #[serde(default)]
+    pub env_files: Vec<String>,
+    /// Shell profile override; falls back to `Settings::default_shell`
+    #[serde(default)]
+    pub shell: Option<ShellProfile>,
+    /// Ids (or names) of projects that must be ready before this one starts
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    #[serde(default)]
+    pub health: HealthChecks,
+    /// Ignored when `restart_on_crash` is false
+    #[serde(default)]
+    pub restart_policy: RestartPolicy,
+    #[serde(default)]
+    pub exit_codes: ExitCodes,
+    /// Signal sent to the whole process group on stop (Unix only)
+    #[serde(default)]
+    pub stop_signal: StopSignal,
+    /// Grace period after `stop_signal` before remaining processes are killed
+    #[serde(default = "default_stop_timeout_ms")]
+    pub stop_timeout_ms: u64,
+    /// Log lines kept in memory per unit; older ones are only in the log files
+    #[serde(default = "default_log_lines")]
+    pub log_lines: usize,
+    /// Output lines logged per second and unit; more are dropped and
+    /// counted. 0 for no limit.
+    #[serde(default = "default_max_log_rate")]
+    pub max_log_rate: u32,
+    /// Run inside a pseudo-terminal instead of pipes (Unix only)
+    #[serde(default)]
+    pub pty: Option<PtyOptions>,
+    #[serde(default)]
+    pub limits: ResourceLimits,
+    #[serde(default)]
+    pub schedule: Option<Schedule>,
+    #[serde(default)]
+    pub watch: Option<WatchConfig>,
+    /// Run these instead of `commands`, each with its own process, log,
+    /// status and restart policy
+    #[serde(default)]
+    pub services: Vec<Service>,
 }
 
 impl Project {
+    /// Restart mode after applying the `restart_on_crash` switch
+    pub fn restart_mode(&self) -> RestartMode {
+        if self.restart_on_crash {
+            self.restart_policy.mode
+        } else {
+            RestartMode::Never
+        }
+    }
+
     pub fn new(name: String, path: String, commands: Vec<String>) -> Self {
         Self {
             id: Uuid::new_v4().to_string(),
@@ -26,8 +581,92 @@ impl Project {
             auto_start: true,
             restart_on_crash: true,
             enabled: true,
+            setup: Vec::new(),
+            setup_on_restart: false,
+            cleanup: Vec::new(),
+            hooks: Hooks::default(),
             env_vars: HashMap::new(),
+            env_files: Vec::new(),
+            shell: None,
+            depends_on: Vec::new(),
+            health: HealthChecks::default(),
+            restart_policy: RestartPolicy::default(),
+            exit_codes: ExitCodes::default(),
+            stop_signal: StopSignal::default(),
+            stop_timeout_ms: default_stop_timeout_ms(),
+            log_lines: default_log_lines(),
+            max_log_rate: default_max_log_rate(),
+            pty: None,
+            limits: ResourceLimits::default(),
+            schedule: None,
+            watch: None,
+            services: Vec::new(),
+        }
+    }
+
+    /// The separately supervised parts of the project: one per service,
+    /// or the project itself when it has no services
+    pub fn units(&self) -> Vec<Project> {
+        if self.services.is_empty() {
+            return vec![self.clone()];
+        }
+        self.services.iter().map(|service| self.service_unit(service)).collect()
+    }
+
+    /// The unit of the service called `name`
+    pub fn find_service_unit(&self, name: &str) -> Option<Project> {
+        self.services
+            .iter()
+            .find(|service| service.name == name)
+            .map(|service| self.service_unit(service))
+    }
+
+    /// A copy of the project that runs one service, under the service's unit id.
+    /// Scheduling and file watching stay with the project.
+    fn service_unit(&self, service: &Service) -> Project {
+        let mut unit = self.clone();
+        unit.id = unit_id(&self.id, Some(&service.name));
+        unit.commands = service.commands.clone();
+        unit.env_vars.extend(service.env_vars.clone());
+        if let Some(restart_on_crash) = service.restart_on_crash {
+            unit.restart_on_crash = restart_on_crash;
+        }
+        if let Some(policy) = &service.restart_policy {
+            unit.restart_policy = policy.clone();
         }
+        if let Some(exit_codes) = &service.exit_codes {
+            unit.exit_codes = exit_codes.clone();
+        }
+        unit.health = service.health.clone();
+        unit.setup = service.setup.clone();
+        unit.cleanup = service.cleanup.clone();
+        unit.schedule = None;
+        unit.watch = None;
+        unit.services = Vec::new();
+        unit
+    }
+
+    /// Check service names, which become part of unit ids
+    fn validate_services(&self) -> Result<(), String> {
+        if self.id.contains('/') {
+            return Err(format!("Project id '{}' must not contain '/'", self.id));
+        }
+        let mut names = std::collections::HashSet::new();
+        for service in &self.services {
+            if service.name.is_empty() || service.name.contains('/') {
+                return Err(format!(
+                    "Project '{}' has an invalid service name '{}'",
+                    self.name, service.name
+                ));
+            }
+            if !names.insert(service.name.as_str()) {
+                return Err(format!(
+                    "Project '{}' has more than one service named '{}'",
+                    self.name, service.name
+                ));
+            }
+        }
+        Ok(())
     }
 }
 
@@ -38,6 +677,93 @@ pub struct Settings {
     pub theme: String,
     pub minimize_to_tray: bool,
     pub show_notifications: bool,
+    #[serde(default)]
+    pub default_shell: ShellProfile,
+    /// Local HTTP API, off unless enabled
+    #[serde(default)]
+    pub api: ApiSettings,
+    #[serde(default)]
+    pub logs: LogSettings,
+}
+
+/// Project output written to `logs/` in the config directory, so it
+/// survives restarts and the in-memory cap
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogSettings {
+    #[serde(default = "default_persist_logs")]
+    pub persist: bool,
+    /// The current file is rotated once it reaches this size...
+    #[serde(default = "default_log_max_file_mb")]
+    pub max_file_mb: u64,
+    /// ...or this age
+    #[serde(default = "default_log_max_file_hours")]
+    pub max_file_hours: u64,
+    /// Gzip rotated files
+    #[serde(default)]
+    pub compress: bool,
+    /// Rotated files older than this are deleted
+    #[serde(default = "default_log_retention_days")]
+    pub retention_days: u64,
+    /// Quota for all projects together; the oldest rotated files go first
+    #[serde(default = "default_log_max_total_mb")]
+    pub max_total_mb: u64,
+}
+
+fn default_persist_logs() -> bool {
+    true
+}
+
+fn default_log_max_file_mb() -> u64 {
+    10
+}
+
+fn default_log_max_file_hours() -> u64 {
+    24
+}
+
+fn default_log_retention_days() -> u64 {
+    14
+}
+
+fn default_log_max_total_mb() -> u64 {
+    500
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            persist: default_persist_logs(),
+            max_file_mb: default_log_max_file_mb(),
+            max_file_hours: default_log_max_file_hours(),
+            compress: false,
+            retention_days: default_log_retention_days(),
+            max_total_mb: default_log_max_total_mb(),
+        }
+    }
+}
+
+/// Local HTTP/JSON API for dashboards and scripts. It only listens on
+/// 127.0.0.1 and needs the token from `api-token` in the config directory.
+/// Changes take effect on the next launch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_api_port")]
+    pub port: u16,
+}
+
+fn default_api_port() -> u16 {
+    7420
+}
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_api_port(),
+        }
+    }
 }
 
 impl Default for Settings {
@@ -47,6 +773,9 @@ impl Default for Settings {
             theme: "dark".to_string(),
             minimize_to_tray: true,
             show_notifications: true,
+            default_shell: ShellProfile::default(),
+            api: ApiSettings::default(),
+            logs: LogSettings::default(),
         }
     }
 }
@@ -59,6 +788,27 @@ pub struct AppConfig {
     pub projects: Vec<Project>,
 }
 
+impl AppConfig {
+    /// Check cross-project references such as `depends_on`, service names,
+    /// schedules and watch globs
+    pub fn validate(&self) -> Result<(), String> {
+        dependency::validate(&self.projects)?;
+        for project in &self.projects {
+            project.validate_services()?;
+            if let Some(schedule) = &project.schedule {
+                schedule
+                    .validate()
+                    .map_err(|e| format!("Project '{}' has an invalid schedule: {}", project.name, e))?;
+            }
+            if let Some(watch) = &project.watch {
+                watcher::Matcher::new(watch)
+                    .map_err(|e| format!("Project '{}' has an invalid watch pattern: {}", project.name, e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
@@ -99,6 +849,8 @@ pub fn load_config() -> AppConfig {
 
 /// Save configuration to file
 pub fn save_config(config: &AppConfig) -> Result<(), String> {
+    config.validate()?;
+
     let path = get_config_path();
     let content = serde_json::to_string_pretty(config)
         .map_err(|e| e.to_string())?;
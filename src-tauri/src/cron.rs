@@ -0,0 +1,259 @@
+//! Cron expression parsing and matching
+//! Standard five-field syntax evaluated in local time at minute resolution
+
+use chrono::{Datelike, Duration, NaiveDateTime, Timelike};
+
+/// Furthest back `last_fire` looks for a match
+const MAX_LOOKBACK_DAYS: i64 = 366;
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// A parsed cron expression: `minute hour day-of-month month day-of-week`.
+///
+/// Fields accept `*`, numbers, ranges (`1-5`), lists (`1,15`) and steps
+/// (`*/10`, `0-30/5`). Months and weekdays also accept three-letter names,
+/// and weekday 7 is Sunday like 0. The `@yearly`, `@monthly`, `@weekly`,
+/// `@daily` and `@hourly` shorthands are supported.
+#[derive(Debug, Clone)]
+pub struct CronExpr {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// When both day fields are restricted, either one matching is enough
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl CronExpr {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let expanded = match expr.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!("expected 5 fields, found {}", fields.len()));
+        };
+
+        let mut weekdays = parse_field(weekday, 0, 7, &WEEKDAY_NAMES, 0)?;
+        // 7 is another name for Sunday
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59, &[], 0)?,
+            hours: parse_field(hour, 0, 23, &[], 0)?,
+            days: parse_field(day, 1, 31, &[], 0)?,
+            months: parse_field(month, 1, 12, &MONTH_NAMES, 1)?,
+            weekdays,
+            // Like Vixie cron, `*/2` still counts as unrestricted
+            days_restricted: !day.starts_with('*'),
+            weekdays_restricted: !weekday.starts_with('*'),
+        })
+    }
+
+    /// Whether the expression fires in the minute containing `time`
+    pub fn matches(&self, time: &NaiveDateTime) -> bool {
+        self.matches_day(time)
+            && has(self.hours, time.hour())
+            && has(self.minutes, time.minute())
+    }
+
+    /// The latest minute in `(after, until]` at which the expression fires
+    pub fn last_fire(&self, after: NaiveDateTime, until: NaiveDateTime) -> Option<NaiveDateTime> {
+        let after = after.max(until - Duration::days(MAX_LOOKBACK_DAYS));
+        let mut time = until.with_second(0)?.with_nanosecond(0)?;
+        while time > after {
+            if !self.matches_day(&time) {
+                // Jump to the last minute of the previous day
+                time = time.date().and_hms_opt(0, 0, 0)? - Duration::minutes(1);
+                continue;
+            }
+            if self.matches(&time) {
+                return Some(time);
+            }
+            time -= Duration::minutes(1);
+        }
+        None
+    }
+
+    fn matches_day(&self, time: &NaiveDateTime) -> bool {
+        if !has(self.months, time.month()) {
+            return false;
+        }
+        let day = has(self.days, time.day());
+        let weekday = has(self.weekdays, time.weekday().num_days_from_sunday());
+        if self.days_restricted && self.weekdays_restricted {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+}
+
+fn has(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+/// Parse one field into a bit set of allowed values.
+/// `names[i]` stands for `first_name + i`.
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str], first_name: u32) -> Result<u64, String> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .map_err(|_| format!("invalid step '{}'", step))?;
+                if step == 0 {
+                    return Err("step must be at least 1".to_string());
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (
+                parse_value(start, names, first_name)?,
+                parse_value(end, names, first_name)?,
+            )
+        } else {
+            let value = parse_value(range, names, first_name)?;
+            // `5/15` means every 15 starting at 5
+            (value, if part.contains('/') { max } else { value })
+        };
+
+        if start < min || end > max || start > end {
+            return Err(format!("'{}' is outside {}-{}", part, min, max));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+fn parse_value(value: &str, names: &[&str], first_name: u32) -> Result<u32, String> {
+    if let Ok(number) = value.parse() {
+        return Ok(number);
+    }
+    let lower = value.to_ascii_lowercase();
+    names
+        .iter()
+        .position(|name| *name == lower)
+        .map(|index| index as u32 + first_name)
+        .ok_or_else(|| format!("invalid value '{}'", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    /// 2024-05-01 is a Wednesday
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 5, day).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn cron(expr: &str) -> CronExpr {
+        CronExpr::parse(expr).unwrap()
+    }
+
+    #[test]
+    fn matches_ranges_steps_and_names() {
+        let expr = cron("*/15 9-17 * * mon-fri");
+        assert!(expr.matches(&at(1, 9, 30)));
+        assert!(expr.matches(&at(1, 17, 45)));
+        assert!(!expr.matches(&at(1, 9, 31)));
+        assert!(!expr.matches(&at(1, 18, 0)));
+        assert!(!expr.matches(&at(4, 9, 30)));
+    }
+
+    #[test]
+    fn a_start_with_a_step_runs_to_the_end() {
+        let expr = cron("5/15 * * * *");
+        let minutes: Vec<u32> = (0..60).filter(|&m| expr.matches(&at(1, 0, m))).collect();
+        assert_eq!(minutes, [5, 20, 35, 50]);
+    }
+
+    #[test]
+    fn either_day_field_matches_when_both_are_restricted() {
+        let expr = cron("0 0 13 * fri");
+        assert!(expr.matches(&at(13, 0, 0)));
+        assert!(expr.matches(&at(3, 0, 0)));
+        assert!(!expr.matches(&at(14, 0, 0)));
+    }
+
+    #[test]
+    fn a_day_field_starting_with_a_star_is_not_a_restriction() {
+        // Odd days that are Mondays, not odd days or Mondays
+        let expr = cron("0 0 */2 * mon");
+        assert!(expr.matches(&at(13, 0, 0)));
+        assert!(!expr.matches(&at(6, 0, 0)));
+        assert!(!expr.matches(&at(3, 0, 0)));
+        // The first week, not the first week or any day
+        let expr = cron("0 0 1-7 * */1");
+        assert!(expr.matches(&at(2, 0, 0)));
+        assert!(!expr.matches(&at(20, 0, 0)));
+    }
+
+    #[test]
+    fn weekday_seven_is_sunday() {
+        let expr = cron("0 12 * * 7");
+        assert!(expr.matches(&at(5, 12, 0)));
+        assert!(!expr.matches(&at(4, 12, 0)));
+    }
+
+    #[test]
+    fn shorthands_expand() {
+        assert!(cron("@hourly").matches(&at(1, 7, 0)));
+        assert!(!cron("@hourly").matches(&at(1, 7, 1)));
+        assert!(cron("@weekly").matches(&at(5, 0, 0)));
+        assert!(cron("@monthly").matches(&at(1, 0, 0)));
+        assert!(!cron("@yearly").matches(&at(1, 0, 0)));
+        assert!(cron("0 0 1 MAY *").matches(&at(1, 0, 0)));
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        let error = |expr| CronExpr::parse(expr).unwrap_err();
+        assert_eq!(error("* * * *"), "expected 5 fields, found 4");
+        assert_eq!(error("60 * * * *"), "'60' is outside 0-59");
+        assert_eq!(error("5-1 * * * *"), "'5-1' is outside 0-59");
+        assert_eq!(error("*/0 * * * *"), "step must be at least 1");
+        assert_eq!(error("*/x * * * *"), "invalid step 'x'");
+        assert_eq!(error("0 0 * foo *"), "invalid value 'foo'");
+    }
+
+    #[test]
+    fn last_fire_finds_the_latest_minute_in_the_window() {
+        let expr = cron("0 9 * * *");
+        assert_eq!(expr.last_fire(at(1, 10, 0), at(3, 8, 59)), Some(at(2, 9, 0)));
+        assert_eq!(expr.last_fire(at(1, 10, 0), at(3, 9, 0)), Some(at(3, 9, 0)));
+        // The start of the window is exclusive
+        assert_eq!(expr.last_fire(at(2, 9, 0), at(3, 8, 59)), None);
+    }
+
+    #[test]
+    fn last_fire_looks_back_up_to_a_year() {
+        let leap_day = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let long_ago = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let expr = cron("0 0 29 2 *");
+        assert_eq!(expr.last_fire(long_ago, at(1, 0, 0)), Some(leap_day));
+        assert_eq!(expr.last_fire(long_ago, leap_day + Duration::days(400)), None);
+    }
+}
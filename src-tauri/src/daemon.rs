@@ -0,0 +1,537 @@
+//! Headless daemon (`devbootd`)
+//! Supervises the configured projects without a window and serves every
+//! command and the event stream over a control socket. The app serves
+//! the same socket while it runs, unless a daemon already does.
+//!
+//! On Unix the socket is a Unix domain socket. Elsewhere it is a TCP
+//! listener on 127.0.0.1, and the socket path names a file holding its port
+//! and a token; a client sends the token as its first line.
+//!
+//! The protocol is newline-delimited JSON. A request is
+//! `{"id": 1, "method": "start_project", "params": {"project_id": "..."}}`,
+//! where `method` is the name of a Tauri command and `params` holds its
+//! arguments in snake_case. Every request gets one response echoing its
+//! `id`: `{"id": 1, "ok": true, "result": ...}` or
+//! `{"id": 1, "ok": false, "error": "..."}`. Requests on one connection are
+//! handled in order.
+//!
+//! The `subscribe` method, with an optional `events` list of event names,
+//! makes the connection also receive `{"event": "...", "payload": {...}}`
+//! lines for every event the window would get. A subscriber that falls
+//! behind receives `{"event": "lagged", "payload": {"missed": n}}`.
+//!
+//! A window opened while a daemon runs does not supervise anything itself:
+//! it forwards its commands to the daemon and shows the daemon's events
+//! (see `Remote`).
+
+use std::io::{BufRead, BufReader, Write};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::async_runtime::block_on;
+use tauri::ipc::{Invoke, InvokeBody, InvokeError};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::commands::AppState;
+use crate::detector;
+use crate::events::Event;
+use crate::startup;
+
+/// Overrides the default socket path for the daemon and its clients
+pub const SOCKET_ENV: &str = "DEVBOOT_SOCKET";
+
+/// A connection to the control socket
+#[cfg(unix)]
+pub type Stream = UnixStream;
+#[cfg(not(unix))]
+pub type Stream = std::net::TcpStream;
+
+/// A bound control socket
+pub struct Listener {
+    #[cfg(unix)]
+    inner: UnixListener,
+    #[cfg(not(unix))]
+    inner: std::net::TcpListener,
+    /// Expected as the first line of every connection
+    #[cfg(not(unix))]
+    token: String,
+}
+
+impl Listener {
+    #[cfg(unix)]
+    fn token(&self) -> Option<String> {
+        None
+    }
+
+    #[cfg(not(unix))]
+    fn token(&self) -> Option<String> {
+        Some(self.token.clone())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Response {
+    id: Value,
+    ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl Response {
+    fn new(id: Value, result: Result<Value, String>) -> Self {
+        match result {
+            Ok(result) => Self {
+                id,
+                ok: true,
+                result: Some(result),
+                error: None,
+            },
+            Err(error) => Self {
+                id,
+                ok: false,
+                result: None,
+                error: Some(error),
+            },
+        }
+    }
+}
+
+/// `$DEVBOOT_SOCKET`, else `devboot/devbootd.sock` in the runtime directory,
+/// falling back to the config directory. Without Unix domain sockets it is
+/// `devboot/devbootd.port` instead.
+pub fn socket_path() -> PathBuf {
+    if let Some(path) = std::env::var_os(SOCKET_ENV) {
+        return PathBuf::from(path);
+    }
+    let name = if cfg!(unix) { "devbootd.sock" } else { "devbootd.port" };
+    dirs::runtime_dir()
+        .or_else(dirs::config_dir)
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("devboot")
+        .join(name)
+}
+
+/// Serve clients on `listener` until SIGINT or SIGTERM, then stop every
+/// project and remove the socket
+pub fn run(state: Arc<AppState>, listener: Listener, socket: &Path) {
+    println!("devbootd listening on {}", socket.display());
+    serve(Arc::clone(&state), listener);
+
+    block_on(shutdown_signal());
+
+    println!("devbootd stopping all projects");
+    block_on(state.stop_all_projects());
+    let _ = std::fs::remove_file(socket);
+}
+
+#[cfg(unix)]
+async fn shutdown_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+        }
+        Err(_) => {
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn shutdown_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// Accept clients on a thread of its own, each served on its own thread
+pub fn serve(state: Arc<AppState>, listener: Listener) {
+    thread::spawn(move || {
+        let token = listener.token();
+        for stream in listener.inner.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let state = Arc::clone(&state);
+            let token = token.clone();
+            thread::spawn(move || serve_connection(&state, stream, token.as_deref()));
+        }
+    });
+}
+
+/// Listen on `socket`, replacing a stale socket file but refusing to take
+/// over from a daemon that still answers. Only the owner may connect.
+#[cfg(unix)]
+pub fn bind(socket: &Path) -> Result<Listener, String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    if let Some(dir) = socket.parent() {
+        if !dir.as_os_str().is_empty() && !dir.exists() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create '{}': {}", dir.display(), e))?;
+            let _ = std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700));
+        }
+    }
+    if socket.exists() {
+        if is_running(socket) {
+            return Err(format!("devbootd is already running on {}", socket.display()));
+        }
+        std::fs::remove_file(socket)
+            .map_err(|e| format!("Failed to remove stale socket '{}': {}", socket.display(), e))?;
+    }
+
+    let inner = UnixListener::bind(socket)
+        .map_err(|e| format!("Failed to listen on '{}': {}", socket.display(), e))?;
+    std::fs::set_permissions(socket, std::fs::Permissions::from_mode(0o600))
+        .map_err(|e| format!("Failed to restrict '{}': {}", socket.display(), e))?;
+    Ok(Listener { inner })
+}
+
+/// Listen on a free port of 127.0.0.1 and record it with a new token in
+/// `socket`, refusing to take over from a daemon that still answers
+#[cfg(not(unix))]
+pub fn bind(socket: &Path) -> Result<Listener, String> {
+    if let Some(dir) = socket.parent() {
+        if !dir.as_os_str().is_empty() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create '{}': {}", dir.display(), e))?;
+        }
+    }
+    if is_running(socket) {
+        return Err(format!("devbootd is already running on {}", socket.display()));
+    }
+
+    let inner = std::net::TcpListener::bind((std::net::Ipv4Addr::LOCALHOST, 0))
+        .map_err(|e| format!("Failed to listen on 127.0.0.1: {}", e))?;
+    let port = inner.local_addr().map_err(|e| e.to_string())?.port();
+    let token = uuid::Uuid::new_v4().simple().to_string();
+    std::fs::write(socket, format!("{}\n{}\n", port, token))
+        .map_err(|e| format!("Failed to write '{}': {}", socket.display(), e))?;
+    Ok(Listener { inner, token })
+}
+
+/// Connect to the control socket
+#[cfg(unix)]
+pub fn connect(socket: &Path) -> std::io::Result<Stream> {
+    UnixStream::connect(socket)
+}
+
+/// Connect to the port recorded in `socket` and send its token
+#[cfg(not(unix))]
+pub fn connect(socket: &Path) -> std::io::Result<Stream> {
+    let endpoint = std::fs::read_to_string(socket)?;
+    let mut lines = endpoint.lines();
+    let port: u16 = lines
+        .next()
+        .and_then(|port| port.trim().parse().ok())
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid port file"))?;
+    let token = lines.next().unwrap_or_default().trim();
+    let mut stream = std::net::TcpStream::connect((std::net::Ipv4Addr::LOCALHOST, port))?;
+    stream.write_all(format!("{}\n", token).as_bytes())?;
+    Ok(stream)
+}
+
+/// Whether a daemon, or a window serving the socket, answers on `socket`
+pub fn is_running(socket: &Path) -> bool {
+    connect(socket).is_ok()
+}
+
+fn serve_connection(state: &AppState, stream: Stream, token: Option<&str>) {
+    let Ok(writer) = stream.try_clone() else {
+        return;
+    };
+    let writer = Arc::new(Mutex::new(writer));
+
+    let mut lines = BufReader::new(stream).lines();
+    if let Some(token) = token {
+        if !lines.next().is_some_and(|line| line.is_ok_and(|line| line.trim() == token)) {
+            let _ = writer.lock().unwrap().shutdown(std::net::Shutdown::Both);
+            return;
+        }
+    }
+    for line in lines {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                let response = Response::new(Value::Null, Err(format!("Invalid request: {}", e)));
+                if send(&writer, &response).is_err() {
+                    break;
+                }
+                continue;
+            }
+        };
+
+        let sent = if request.method == "subscribe" {
+            subscribe(state, request, &writer)
+        } else {
+            let result = dispatch(state, &request.method, &request.params);
+            send(&writer, &Response::new(request.id, result))
+        };
+        if sent.is_err() {
+            break;
+        }
+    }
+
+    // Ends the event forwarder, if any, on its next write
+    let _ = writer.lock().unwrap().shutdown(std::net::Shutdown::Both);
+}
+
+fn send<T: Serialize>(writer: &Mutex<Stream>, message: &T) -> std::io::Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    writer.lock().unwrap().write_all(&line)
+}
+
+/// Acknowledge a `subscribe` request, then forward events on a thread of
+/// their own until the client goes away
+fn subscribe(state: &AppState, request: Request, writer: &Arc<Mutex<Stream>>) -> std::io::Result<()> {
+    let filter: Option<Vec<String>> = match param(&request.params, "events") {
+        Ok(filter) => filter,
+        Err(e) => return send(writer, &Response::new(request.id, Err(e))),
+    };
+    // Subscribe before acknowledging so no event in between is missed
+    let mut events = state.process_manager.subscribe();
+    send(writer, &Response::new(request.id, Ok(Value::Null)))?;
+
+    let writer = Arc::clone(writer);
+    thread::spawn(move || loop {
+        let event = match events.blocking_recv() {
+            Ok(event) => event,
+            Err(RecvError::Lagged(missed)) => Event {
+                event: "lagged".to_string(),
+                payload: json!({ "missed": missed }),
+            },
+            Err(RecvError::Closed) => return,
+        };
+        if filter.as_ref().is_some_and(|names| !names.contains(&event.event)) && event.event != "lagged" {
+            continue;
+        }
+        if send(&writer, &event).is_err() {
+            return;
+        }
+    });
+    Ok(())
+}
+
+/// Read a named parameter; a missing one reads as `null`
+fn param<T: DeserializeOwned>(params: &Value, name: &str) -> Result<T, String> {
+    let value = params.get(name).cloned().unwrap_or(Value::Null);
+    serde_json::from_value(value).map_err(|e| format!("Invalid parameter '{}': {}", name, e))
+}
+
+fn to_result<T: Serialize>(value: T) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|e| e.to_string())
+}
+
+fn reply<T: Serialize>(result: Result<T, String>) -> Result<Value, String> {
+    to_result(result?)
+}
+
+/// Run one command by name, as the window would through `invoke`.
+/// Connections are served on threads of their own, outside the runtime,
+/// so async commands are waited on here.
+fn dispatch(state: &AppState, method: &str, params: &Value) -> Result<Value, String> {
+    let p = params;
+    match method {
+        // Config commands
+        "get_config" => to_result(state.get_config()),
+        "save_config_cmd" => reply(state.save_config(param(p, "config")?)),
+        "get_projects" => to_result(state.get_projects()),
+        "add_project" => reply(state.add_project(
+            param(p, "name")?,
+            param(p, "path")?,
+            param(p, "commands")?,
+        )),
+        "update_project" => reply(state.update_project(param(p, "project")?)),
+        "delete_project" => reply(block_on(state.delete_project(param(p, "project_id")?))),
+        "get_settings" => to_result(state.get_settings()),
+        "update_settings" => reply(state.update_settings(param(p, "settings")?)),
+        // Process commands
+        "start_project" => reply(state.start_project(param(p, "project_id")?)),
+        "stop_project" => reply(block_on(state.stop_project(param(p, "project_id")?))),
+        "restart_project" => reply(block_on(state.restart_project(param(p, "project_id")?))),
+        "start_service" => reply(state.start_service(param(p, "project_id")?, param(p, "service")?)),
+        "stop_service" => reply(block_on(state.stop_service(param(p, "project_id")?, param(p, "service")?))),
+        "restart_service" => reply(block_on(state.restart_service(param(p, "project_id")?, param(p, "service")?))),
+        "get_service_statuses" => to_result(state.get_service_statuses(param(p, "project_id")?)),
+        "get_project_status" => to_result(state.get_project_status(param(p, "project_id")?, param(p, "service")?)),
+        "get_project_logs" => to_result(state.get_project_logs(param(p, "project_id")?, param(p, "service")?)),
+        "get_log_page" => to_result(state.get_log_page(
+            param(p, "project_id")?,
+            param(p, "service")?,
+            param(p, "cursor")?,
+            param(p, "direction")?,
+            param(p, "limit")?,
+        )),
+        "search_logs" => reply(block_on(state.search_logs(param(p, "query")?))),
+        "get_project_stats" => to_result(state.get_project_stats(param(p, "project_id")?, param(p, "service")?)),
+        "get_breaker_status" => to_result(state.get_breaker_status(param(p, "project_id")?, param(p, "service")?)),
+        "clear_project_logs" => {
+            state.clear_project_logs(param(p, "project_id")?, param(p, "service")?);
+            Ok(Value::Null)
+        }
+        "send_project_input" => reply(block_on(state.send_project_input(
+            param(p, "project_id")?,
+            param(p, "service")?,
+            param(p, "input")?,
+        ))),
+        "send_project_interrupt" => reply(block_on(state.send_project_interrupt(param(p, "project_id")?, param(p, "service")?))),
+        "send_project_control" => reply(block_on(state.send_project_control(
+            param(p, "project_id")?,
+            param(p, "service")?,
+            param(p, "key")?,
+        ))),
+        "resize_project_terminal" => reply(state.resize_project_terminal(
+            param(p, "project_id")?,
+            param(p, "service")?,
+            param(p, "cols")?,
+            param(p, "rows")?,
+        )),
+        "stop_all_projects" => {
+            block_on(state.stop_all_projects());
+            Ok(Value::Null)
+        }
+        // Startup commands
+        "enable_auto_start" => reply(startup::enable_auto_start()),
+        "disable_auto_start" => reply(startup::disable_auto_start()),
+        "is_auto_start_enabled" => to_result(startup::is_auto_start_enabled()),
+        // Detection commands
+        "detect_project_from_path" => to_result(detector::detect_project(&param::<String>(p, "path")?)),
+        _ => Err(format!("Unknown method: {}", method)),
+    }
+}
+
+/// The window's link to a daemon that already supervises the projects.
+/// Each command goes over a connection of its own, so a slow stop does not
+/// hold up the others, and events arrive over one subscription.
+pub struct Remote {
+    socket: PathBuf,
+}
+
+impl Remote {
+    pub fn new(socket: PathBuf) -> Self {
+        Self { socket }
+    }
+
+    /// Answer an `invoke` from the window with the daemon's response
+    pub fn invoke(&self, invoke: Invoke) -> bool {
+        let method = invoke.message.command().to_string();
+        // `invoke` passes arguments in camelCase, the protocol takes snake_case
+        let params = match invoke.message.payload() {
+            InvokeBody::Json(Value::Object(args)) => {
+                Value::Object(args.iter().map(|(name, value)| (snake_case(name), value.clone())).collect())
+            }
+            _ => Value::Null,
+        };
+        let socket = self.socket.clone();
+        invoke.resolver.respond_async(async move {
+            tauri::async_runtime::spawn_blocking(move || call(&socket, &method, params))
+                .await
+                .map_err(|e| InvokeError::from(e.to_string()))?
+                .map_err(InvokeError::from)
+        });
+        true
+    }
+
+    /// Emit the daemon's events to the window, on a thread of their own
+    pub fn relay_events(&self, app: AppHandle) {
+        let socket = self.socket.clone();
+        thread::spawn(move || {
+            if let Err(e) = relay(&socket, &app) {
+                eprintln!("Lost the connection to devbootd: {}", e);
+            }
+        });
+    }
+}
+
+fn open(socket: &Path) -> Result<(BufReader<Stream>, Stream), String> {
+    let stream = connect(socket).map_err(|e| format!("Cannot reach devbootd: {}", e))?;
+    let writer = stream.try_clone().map_err(|e| e.to_string())?;
+    Ok((BufReader::new(stream), writer))
+}
+
+fn request(writer: &mut Stream, method: &str, params: Value) -> Result<(), String> {
+    let mut line = json!({ "id": 1, "method": method, "params": params }).to_string();
+    line.push('\n');
+    writer
+        .write_all(line.as_bytes())
+        .map_err(|e| format!("Failed to send to devbootd: {}", e))
+}
+
+fn read_line(reader: &mut BufReader<Stream>) -> Result<String, String> {
+    let mut line = String::new();
+    match reader.read_line(&mut line) {
+        Ok(0) => Err("devbootd closed the connection".to_string()),
+        Ok(_) => Ok(line),
+        Err(e) => Err(format!("Failed to read from devbootd: {}", e)),
+    }
+}
+
+fn response(reader: &mut BufReader<Stream>) -> Result<Value, String> {
+    let line = read_line(reader)?;
+    let response: Response =
+        serde_json::from_str(&line).map_err(|e| format!("Invalid response from devbootd: {}", e))?;
+    match response.error {
+        Some(error) if !response.ok => Err(error),
+        _ => Ok(response.result.unwrap_or(Value::Null)),
+    }
+}
+
+/// Send one request on a new connection and wait for its response
+fn call(socket: &Path, method: &str, params: Value) -> Result<Value, String> {
+    let (mut reader, mut writer) = open(socket)?;
+    request(&mut writer, method, params)?;
+    response(&mut reader)
+}
+
+fn relay(socket: &Path, app: &AppHandle) -> Result<(), String> {
+    let (mut reader, mut writer) = open(socket)?;
+    request(&mut writer, "subscribe", Value::Null)?;
+    response(&mut reader)?;
+    loop {
+        let line = read_line(&mut reader)?;
+        let Ok(event) = serde_json::from_str::<Event>(&line) else {
+            continue;
+        };
+        let _ = app.emit(&event.event, event.payload);
+    }
+}
+
+/// `projectId` as `project_id`
+fn snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            snake.push('_');
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
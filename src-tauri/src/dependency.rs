@@ -0,0 +1,214 @@
+//! Project dependency graph
+//! Validates `depends_on` references and computes start/stop order
+
+use std::collections::{HashMap, HashSet};
+
+use crate::config::Project;
+
+/// Find a project by id, falling back to its name
+pub fn find_project<'a>(projects: &'a [Project], key: &str) -> Option<&'a Project> {
+    projects
+        .iter()
+        .find(|p| p.id == key)
+        .or_else(|| projects.iter().find(|p| p.name == key))
+}
+
+/// Check that every dependency exists and that the graph has no cycles
+pub fn validate(projects: &[Project]) -> Result<(), String> {
+    for project in projects {
+        for dep in &project.depends_on {
+            match find_project(projects, dep) {
+                Some(target) if target.id == project.id => {
+                    return Err(format!("Project '{}' cannot depend on itself", project.name));
+                }
+                Some(_) => {}
+                None => {
+                    return Err(format!(
+                        "Project '{}' depends on unknown project '{}'",
+                        project.name, dep
+                    ));
+                }
+            }
+        }
+    }
+
+    let roots: Vec<&str> = projects.iter().map(|p| p.id.as_str()).collect();
+    start_order(projects, &roots).map(|_| ())
+}
+
+/// Projects needed to start `roots`, dependencies first.
+///
+/// Includes every transitive dependency of the roots. Fails with the cycle
+/// path (e.g. `api -> worker -> api`) if the graph is not acyclic.
+pub fn start_order(projects: &[Project], roots: &[&str]) -> Result<Vec<Project>, String> {
+    let mut order = Vec::new();
+    let mut done = HashSet::new();
+    let mut path = Vec::new();
+
+    for root in roots {
+        let project = find_project(projects, root)
+            .ok_or_else(|| format!("Project not found: {}", root))?;
+        visit(projects, project, &mut path, &mut done, &mut order)?;
+    }
+
+    Ok(order)
+}
+
+/// Depth-first post-order walk; `path` holds the projects on the current branch
+fn visit<'a>(
+    projects: &'a [Project],
+    project: &'a Project,
+    path: &mut Vec<&'a Project>,
+    done: &mut HashSet<String>,
+    order: &mut Vec<Project>,
+) -> Result<(), String> {
+    if done.contains(&project.id) {
+        return Ok(());
+    }
+
+    if let Some(pos) = path.iter().position(|p| p.id == project.id) {
+        let cycle: Vec<&str> = path[pos..]
+            .iter()
+            .chain(std::iter::once(&project))
+            .map(|p| p.name.as_str())
+            .collect();
+        return Err(format!("Dependency cycle detected: {}", cycle.join(" -> ")));
+    }
+
+    path.push(project);
+    for dep in &project.depends_on {
+        if let Some(dep_project) = find_project(projects, dep) {
+            visit(projects, dep_project, path, done, order)?;
+        }
+    }
+    path.pop();
+
+    done.insert(project.id.clone());
+    order.push(project.clone());
+    Ok(())
+}
+
+/// Ids of `roots` and everything that transitively depends on them, in the
+/// order they should be stopped (dependents before their dependencies).
+pub fn stop_order(projects: &[Project], roots: &[&str]) -> Vec<String> {
+    // Reverse edges: dependency id -> ids of projects that depend on it
+    let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+    for project in projects {
+        for dep in &project.depends_on {
+            if let Some(dep_project) = find_project(projects, dep) {
+                dependents
+                    .entry(dep_project.id.as_str())
+                    .or_default()
+                    .push(project.id.as_str());
+            }
+        }
+    }
+
+    // Collect the affected set
+    let mut affected = HashSet::new();
+    let mut stack: Vec<&str> = roots
+        .iter()
+        .filter_map(|root| find_project(projects, root))
+        .map(|p| p.id.as_str())
+        .collect();
+    while let Some(id) = stack.pop() {
+        if affected.insert(id) {
+            stack.extend(dependents.get(id).into_iter().flatten().copied());
+        }
+    }
+
+    // Reverse start order restricted to the affected set. A cyclic graph
+    // cannot be saved, but fall back to the plain list rather than failing.
+    let affected_ids: Vec<&str> = affected.iter().copied().collect();
+    match start_order(projects, &affected_ids) {
+        Ok(order) => order
+            .into_iter()
+            .rev()
+            .filter(|p| affected.contains(p.id.as_str()))
+            .map(|p| p.id)
+            .collect(),
+        Err(_) => affected_ids.into_iter().map(str::to_string).collect(),
+    }
+}
+
+/// Ids of projects that other projects in `plan` depend on
+pub fn required_by_others(plan: &[Project]) -> HashSet<String> {
+    plan.iter()
+        .flat_map(|p| p.depends_on.iter())
+        .filter_map(|dep| find_project(plan, dep))
+        .map(|p| p.id.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A project with id `id-<name>` depending on `deps` (names or ids)
+    fn project(name: &str, deps: &[&str]) -> Project {
+        let mut project = Project::new(name.to_string(), "/tmp".to_string(), Vec::new());
+        project.id = format!("id-{}", name);
+        project.depends_on = deps.iter().map(|dep| dep.to_string()).collect();
+        project
+    }
+
+    fn names(order: &[Project]) -> Vec<&str> {
+        order.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn starts_dependencies_first_and_once() {
+        let projects = [
+            project("web", &["api", "id-cache"]),
+            project("api", &["db", "cache"]),
+            project("db", &[]),
+            project("cache", &[]),
+            project("other", &[]),
+        ];
+        let order = start_order(&projects, &["web", "id-db"]).unwrap();
+        assert_eq!(names(&order), ["db", "cache", "api", "web"]);
+    }
+
+    #[test]
+    fn reports_the_cycle_path() {
+        let projects = [
+            project("web", &["a"]),
+            project("a", &["b"]),
+            project("b", &["c"]),
+            project("c", &["a"]),
+        ];
+        assert_eq!(
+            start_order(&projects, &["web"]).unwrap_err(),
+            "Dependency cycle detected: a -> b -> c -> a"
+        );
+        assert_eq!(
+            validate(&projects).unwrap_err(),
+            "Dependency cycle detected: a -> b -> c -> a"
+        );
+    }
+
+    #[test]
+    fn rejects_self_and_unknown_dependencies() {
+        assert_eq!(
+            validate(&[project("a", &["a"])]).unwrap_err(),
+            "Project 'a' cannot depend on itself"
+        );
+        assert_eq!(
+            validate(&[project("a", &["missing"])]).unwrap_err(),
+            "Project 'a' depends on unknown project 'missing'"
+        );
+        assert_eq!(start_order(&[], &["missing"]).unwrap_err(), "Project not found: missing");
+    }
+
+    #[test]
+    fn stops_dependents_first() {
+        let projects = [
+            project("web", &["api"]),
+            project("api", &["db"]),
+            project("db", &[]),
+            project("other", &[]),
+        ];
+        assert_eq!(stop_order(&projects, &["db"]), ["id-web", "id-api", "id-db"]);
+        assert_eq!(stop_order(&projects, &["api"]), ["id-web", "id-api"]);
+    }
+}
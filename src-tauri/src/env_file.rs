@@ -0,0 +1,215 @@
+//! Environment loading for spawned projects
+//! Parses dotenv-style files and merges them with per-project env vars
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::config::Project;
+
+/// Resolve the environment for a project spawn.
+///
+/// Files in `env_files` are loaded in order, so later files override earlier
+/// ones, and `env_vars` override everything loaded from files. Relative file
+/// paths are resolved against the project path. Returns the merged variables
+/// together with human-readable problems (missing files, syntax errors).
+pub fn resolve_project_env(project: &Project) -> (HashMap<String, String>, Vec<String>) {
+    let mut env = HashMap::new();
+    let mut problems = Vec::new();
+
+    for file in &project.env_files {
+        let file_path = Path::new(&project.path).join(file);
+        match std::fs::read_to_string(&file_path) {
+            Ok(content) => {
+                let (vars, errors) = parse_env(&content);
+                env.extend(vars);
+                for error in errors {
+                    problems.push(format!("{}:{}: {}", file, error.line, error.message));
+                }
+            }
+            Err(e) => problems.push(format!("{}: failed to read env file: {}", file, e)),
+        }
+    }
+
+    env.extend(project.env_vars.clone());
+    (env, problems)
+}
+
+/// A syntax error on a single line of an env file
+#[derive(Debug, Clone)]
+pub struct EnvParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Parse dotenv-style content.
+///
+/// Supports `KEY=value`, an optional `export ` prefix, `#` comments,
+/// single-quoted literal values and double-quoted values with `\n`, `\t`,
+/// `\"` and `\\` escapes. Returns the parsed pairs in file order and a list
+/// of syntax errors for lines that were skipped.
+pub fn parse_env(content: &str) -> (Vec<(String, String)>, Vec<EnvParseError>) {
+    let mut vars = Vec::new();
+    let mut errors = Vec::new();
+
+    for (index, raw_line) in content.lines().enumerate() {
+        let line_no = index + 1;
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").map(str::trim_start).unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            errors.push(EnvParseError { line: line_no, message: "expected KEY=VALUE".to_string() });
+            continue;
+        };
+
+        let key = key.trim();
+        if !is_valid_key(key) {
+            errors.push(EnvParseError { line: line_no, message: format!("invalid variable name '{}'", key) });
+            continue;
+        }
+
+        match parse_value(value.trim()) {
+            Ok(value) => vars.push((key.to_string(), value)),
+            Err(message) => errors.push(EnvParseError { line: line_no, message }),
+        }
+    }
+
+    (vars, errors)
+}
+
+fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+fn parse_value(value: &str) -> Result<String, String> {
+    if let Some(rest) = value.strip_prefix('\'') {
+        let end = rest.find('\'').ok_or("unterminated single-quoted value")?;
+        check_trailing(&rest[end + 1..])?;
+        return Ok(rest[..end].to_string());
+    }
+
+    if let Some(rest) = value.strip_prefix('"') {
+        let mut result = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    check_trailing(&rest[i + 1..])?;
+                    return Ok(result);
+                }
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => result.push('\n'),
+                    Some((_, 't')) => result.push('\t'),
+                    Some((_, 'r')) => result.push('\r'),
+                    Some((_, other)) => result.push(other),
+                    None => break,
+                },
+                _ => result.push(c),
+            }
+        }
+        return Err("unterminated double-quoted value".to_string());
+    }
+
+    // Unquoted: strip inline comments that follow whitespace
+    let value = match value.find(" #") {
+        Some(pos) => &value[..pos],
+        None => value,
+    };
+    Ok(value.trim_end().to_string())
+}
+
+/// Only whitespace or a comment may follow a closing quote
+fn check_trailing(rest: &str) -> Result<(), String> {
+    let rest = rest.trim_start();
+    if rest.is_empty() || rest.starts_with('#') {
+        Ok(())
+    } else {
+        Err(format!("unexpected characters after quoted value: '{}'", rest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(content: &str) -> Vec<(String, String)> {
+        let (vars, errors) = parse_env(content);
+        assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+        vars
+    }
+
+    fn pair(key: &str, value: &str) -> (String, String) {
+        (key.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn parses_plain_pairs_in_order() {
+        let content = "# comment\n\nPORT=3000\nexport  NODE_ENV = production\nEMPTY=\n";
+        assert_eq!(
+            vars(content),
+            vec![pair("PORT", "3000"), pair("NODE_ENV", "production"), pair("EMPTY", "")]
+        );
+    }
+
+    #[test]
+    fn strips_inline_comments_only_after_whitespace() {
+        assert_eq!(
+            vars("A=value # note\nB=a#b\nC=x=y"),
+            vec![pair("A", "value"), pair("B", "a#b"), pair("C", "x=y")]
+        );
+    }
+
+    #[test]
+    fn single_quotes_are_literal() {
+        assert_eq!(vars(r"A='it\n is # kept' # note"), vec![pair("A", r"it\n is # kept")]);
+    }
+
+    #[test]
+    fn double_quotes_unescape() {
+        assert_eq!(
+            vars(r#"A="line\nnext\t\"q\" \\ # kept""#),
+            vec![pair("A", "line\nnext\t\"q\" \\ # kept")]
+        );
+    }
+
+    #[test]
+    fn reports_errors_by_line_and_keeps_going() {
+        let content = "GOOD=1\nno equals sign\n1BAD=x\nA=\"open\nB='x' y\nLAST=2";
+        let (vars, errors) = parse_env(content);
+        assert_eq!(vars, vec![pair("GOOD", "1"), pair("LAST", "2")]);
+        let lines: Vec<usize> = errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![2, 3, 4, 5]);
+        assert_eq!(errors[0].message, "expected KEY=VALUE");
+        assert_eq!(errors[1].message, "invalid variable name '1BAD'");
+        assert_eq!(errors[2].message, "unterminated double-quoted value");
+        assert_eq!(errors[3].message, "unexpected characters after quoted value: 'y'");
+    }
+
+    #[test]
+    fn later_files_and_env_vars_override() {
+        let dir = std::env::temp_dir().join(format!("devboot-env-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(".env"), "A=file\nB=file\nC=file\n").unwrap();
+        std::fs::write(dir.join(".env.local"), "B=local\nbroken\n").unwrap();
+
+        let mut project = Project::new("env".to_string(), dir.to_string_lossy().into_owned(), Vec::new());
+        project.env_files = vec![".env".to_string(), ".env.local".to_string(), "missing.env".to_string()];
+        project.env_vars = HashMap::from([("C".to_string(), "var".to_string())]);
+        let (env, problems) = resolve_project_env(&project);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(env["A"], "file");
+        assert_eq!(env["B"], "local");
+        assert_eq!(env["C"], "var");
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0], ".env.local:2: expected KEY=VALUE");
+        assert!(problems[1].starts_with("missing.env: failed to read env file"));
+    }
+}
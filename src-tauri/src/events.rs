@@ -0,0 +1,76 @@
+//! Event delivery
+//! Process events go to the window, when there is one, and to every
+//! subscriber of the control socket
+
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{broadcast, watch};
+
+/// Events a subscriber may fall behind by before it misses some
+const SUBSCRIBER_BUFFER: usize = 4096;
+
+/// An emitted event as sent to subscribers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    pub event: String,
+    pub payload: serde_json::Value,
+}
+
+pub struct EventBus {
+    app_handle: Mutex<Option<AppHandle>>,
+    subscribers: broadcast::Sender<Event>,
+    /// Bumped whenever a unit's status changes, for tasks waiting on one
+    status_changes: watch::Sender<()>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self {
+            app_handle: Mutex::new(None),
+            subscribers: broadcast::channel(SUBSCRIBER_BUFFER).0,
+            status_changes: watch::Sender::new(()),
+        }
+    }
+
+    pub fn set_app_handle(&self, handle: AppHandle) {
+        *self.app_handle.lock().unwrap() = Some(handle);
+    }
+
+    /// Receive every event emitted from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.subscribers.subscribe()
+    }
+
+    /// Wakes on every status change from now on; read the status itself
+    /// from the process table
+    pub fn watch_status(&self) -> watch::Receiver<()> {
+        self.status_changes.subscribe()
+    }
+
+    pub fn status_changed(&self) {
+        self.status_changes.send_replace(());
+    }
+
+    pub fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        // Payloads are only serialized for subscribers when there are any
+        if self.subscribers.receiver_count() > 0 {
+            if let Ok(value) = serde_json::to_value(&payload) {
+                let _ = self.subscribers.send(Event {
+                    event: event.to_string(),
+                    payload: value,
+                });
+            }
+        }
+        if let Some(handle) = self.app_handle.lock().unwrap().as_ref() {
+            let _ = handle.emit(event, payload);
+        }
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
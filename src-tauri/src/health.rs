@@ -0,0 +1,226 @@
+//! Health probes for running projects
+//! HTTP, TCP and command checks used for readiness and liveness
+
+use std::collections::HashMap;
+use std::io;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::config::{Probe, ProbeCheck, ShellProfile};
+use crate::process_group::{self, ExitDetails};
+use crate::shell::ShellInvocation;
+
+/// A probe check prepared for repeated evaluation
+enum CompiledCheck {
+    Http { url: String, expected_status: u16 },
+    Tcp { host: String, port: u16 },
+    LogPattern(Regex),
+    Command(ShellInvocation),
+}
+
+/// Schedules one configured probe and tracks its consecutive failures
+pub struct ProbeRunner {
+    check: CompiledCheck,
+    interval: Duration,
+    timeout: Duration,
+    next_at: Instant,
+    log_cursor: u64,
+    failures: u32,
+    failure_threshold: u32,
+}
+
+impl ProbeRunner {
+    /// Prepare a probe; `log_cursor` is the log position the run started at
+    pub fn new(probe: &Probe, shell: ShellProfile, log_cursor: u64) -> Result<Self, String> {
+        let check = match &probe.check {
+            ProbeCheck::Http { url, expected_status } => CompiledCheck::Http {
+                url: url.clone(),
+                expected_status: *expected_status,
+            },
+            ProbeCheck::Tcp { host, port } => CompiledCheck::Tcp {
+                host: host.clone(),
+                port: *port,
+            },
+            ProbeCheck::LogPattern { pattern } => CompiledCheck::LogPattern(
+                Regex::new(pattern).map_err(|e| format!("Invalid probe pattern: {}", e))?,
+            ),
+            ProbeCheck::Command { command } => {
+                CompiledCheck::Command(ShellInvocation::new(shell, std::slice::from_ref(command))?)
+            }
+        };
+
+        Ok(Self {
+            check,
+            interval: Duration::from_millis(probe.interval_ms.max(100)),
+            timeout: Duration::from_millis(probe.timeout_ms.max(100)),
+            next_at: Instant::now() + Duration::from_millis(probe.initial_delay_ms),
+            log_cursor,
+            failures: 0,
+            failure_threshold: probe.failure_threshold.max(1),
+        })
+    }
+
+    /// When the probe is next due
+    pub fn next_at(&self) -> Instant {
+        self.next_at
+    }
+
+    /// Run the probe if it is due, returning `None` otherwise.
+    ///
+    /// `new_lines` returns the log lines written after a cursor together with
+    /// the new cursor; it is only called for log pattern probes.
+    pub async fn poll<F>(
+        &mut self,
+        cwd: &str,
+        env: &HashMap<String, String>,
+        new_lines: F,
+    ) -> Option<Result<(), String>>
+    where
+        F: FnOnce(u64) -> (Vec<String>, u64),
+    {
+        let now = Instant::now();
+        if now < self.next_at {
+            return None;
+        }
+        self.next_at = now + self.interval;
+
+        let result = match &self.check {
+            CompiledCheck::Http { url, expected_status } => {
+                check_http(url, *expected_status, self.timeout).await
+            }
+            CompiledCheck::Tcp { host, port } => check_tcp(host, *port, self.timeout).await,
+            CompiledCheck::LogPattern(pattern) => {
+                let (lines, cursor) = new_lines(self.log_cursor);
+                self.log_cursor = cursor;
+                if lines.iter().any(|line| pattern.is_match(line)) {
+                    Ok(())
+                } else {
+                    Err("No matching log line since the last check".to_string())
+                }
+            }
+            CompiledCheck::Command(invocation) => check_command(invocation, cwd, env, self.timeout).await,
+        };
+
+        match result {
+            Ok(()) => self.failures = 0,
+            Err(_) => self.failures += 1,
+        }
+        Some(result)
+    }
+
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    pub fn failure_threshold(&self) -> u32 {
+        self.failure_threshold
+    }
+}
+
+/// Send `GET` to an `http://` URL and compare the response status
+pub async fn check_http(url: &str, expected_status: u16, timeout: Duration) -> Result<(), String> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| format!("Only http:// URLs are supported: {}", url))?;
+    let (authority, path) = match rest.find('/') {
+        Some(pos) => (&rest[..pos], &rest[pos..]),
+        None => (rest, "/"),
+    };
+    let address = if authority.contains(':') {
+        authority.to_string()
+    } else {
+        format!("{}:80", authority)
+    };
+
+    let mut stream = connect(&address, timeout).await?;
+
+    let request = format!(
+        "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: DevBoot\r\nConnection: close\r\n\r\n",
+        path, authority
+    );
+    tokio::time::timeout(timeout, stream.write_all(request.as_bytes()))
+        .await
+        .unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into()))
+        .map_err(|e| format!("HTTP request failed: {}", e))?;
+
+    // Only the status line is needed
+    let mut buf = [0u8; 256];
+    let n = tokio::time::timeout(timeout, stream.read(&mut buf))
+        .await
+        .unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into()))
+        .map_err(|e| format!("HTTP response failed: {}", e))?;
+    let response = String::from_utf8_lossy(&buf[..n]);
+    let status: u16 = response
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| "Malformed HTTP response".to_string())?;
+
+    if status == expected_status {
+        Ok(())
+    } else {
+        Err(format!("HTTP status {} (expected {})", status, expected_status))
+    }
+}
+
+/// Open a TCP connection to `host:port`
+pub async fn check_tcp(host: &str, port: u16, timeout: Duration) -> Result<(), String> {
+    connect(&format!("{}:{}", host, port), timeout).await.map(|_| ())
+}
+
+/// Run a probe command; exit code 0 means healthy. The command gets a
+/// process group of its own so a timeout also kills what it started.
+pub async fn check_command(
+    invocation: &ShellInvocation,
+    cwd: &str,
+    env: &HashMap<String, String>,
+    timeout: Duration,
+) -> Result<(), String> {
+    let mut command = invocation.to_command();
+    process_group::set_new_group(&mut command);
+    command
+        .current_dir(cwd)
+        .envs(env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    let mut child = tokio::process::Command::from(command)
+        .spawn()
+        .map_err(|e| format!("Failed to run probe command: {}", e))?;
+
+    match tokio::time::timeout(timeout, child.wait()).await {
+        Ok(Ok(status)) if status.success() => Ok(()),
+        Ok(Ok(status)) => Err(format!(
+            "Probe command exited with {}",
+            ExitDetails::from_status(&status)
+        )),
+        Ok(Err(e)) => Err(format!("Failed to wait for probe command: {}", e)),
+        Err(_) => {
+            if let Some(pid) = child.id() {
+                process_group::kill_tree(pid);
+            }
+            let _ = child.kill().await;
+            Err(format!("Probe command timed out after {}ms", timeout.as_millis()))
+        }
+    }
+}
+
+async fn connect(address: &str, timeout: Duration) -> Result<TcpStream, String> {
+    let addrs = tokio::net::lookup_host(address)
+        .await
+        .map_err(|e| format!("Cannot resolve {}: {}", address, e))?;
+
+    let mut last_error = format!("No addresses for {}", address);
+    for addr in addrs {
+        match tokio::time::timeout(timeout, TcpStream::connect(addr)).await {
+            Ok(Ok(stream)) => return Ok(stream),
+            Ok(Err(e)) => last_error = format!("Cannot connect to {}: {}", addr, e),
+            Err(_) => last_error = format!("Cannot connect to {}: timed out", addr),
+        }
+    }
+    Err(last_error)
+}
@@ -0,0 +1,87 @@
+//! Lifecycle hooks
+//! User commands run when a project starts, crashes, gives up or stops
+
+use std::collections::HashMap;
+
+use crate::config::{self, Hooks, Project};
+use crate::process_group::ExitDetails;
+
+/// Points of a project's lifecycle that can run a hook
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    BeforeStart,
+    AfterStart,
+    Crash,
+    GiveUp,
+    AfterStop,
+}
+
+impl HookEvent {
+    /// Name of the hook in the config, also used as its log marker
+    pub fn name(self) -> &'static str {
+        match self {
+            HookEvent::BeforeStart => "before_start",
+            HookEvent::AfterStart => "after_start",
+            HookEvent::Crash => "on_crash",
+            HookEvent::GiveUp => "on_give_up",
+            HookEvent::AfterStop => "after_stop",
+        }
+    }
+
+    /// The configured command, unless it is missing or blank
+    pub fn command(self, hooks: &Hooks) -> Option<&str> {
+        let command = match self {
+            HookEvent::BeforeStart => &hooks.before_start,
+            HookEvent::AfterStart => &hooks.after_start,
+            HookEvent::Crash => &hooks.on_crash,
+            HookEvent::GiveUp => &hooks.on_give_up,
+            HookEvent::AfterStop => &hooks.after_stop,
+        };
+        command.as_deref().filter(|command| !command.trim().is_empty())
+    }
+}
+
+/// A hook to run and the details passed to it
+#[derive(Debug, Clone, Copy)]
+pub struct HookRun {
+    pub event: HookEvent,
+    /// How the run that ended exited, if the event follows one
+    pub exit: Option<ExitDetails>,
+    pub restart_count: u32,
+}
+
+impl HookRun {
+    pub fn new(event: HookEvent, exit: Option<ExitDetails>, restart_count: u32) -> Self {
+        Self {
+            event,
+            exit,
+            restart_count,
+        }
+    }
+
+    /// Variables describing the event, set on top of the project's environment
+    pub fn env(&self, project: &Project) -> HashMap<String, String> {
+        let (project_id, service) = config::split_unit_id(&project.id);
+        let mut env = HashMap::from([
+            ("DEVBOOT_HOOK".to_string(), self.event.name().to_string()),
+            ("DEVBOOT_PROJECT_ID".to_string(), project_id.to_string()),
+            ("DEVBOOT_PROJECT_NAME".to_string(), project.name.clone()),
+            ("DEVBOOT_RESTART_COUNT".to_string(), self.restart_count.to_string()),
+        ]);
+        if let Some(service) = service {
+            env.insert("DEVBOOT_SERVICE".to_string(), service.to_string());
+        }
+        if let Some(exit) = self.exit {
+            if let Some(code) = exit.code {
+                env.insert("DEVBOOT_EXIT_CODE".to_string(), code.to_string());
+            }
+            if let Some(signal) = exit.signal_name() {
+                env.insert("DEVBOOT_SIGNAL".to_string(), signal);
+            }
+            if exit.core_dumped {
+                env.insert("DEVBOOT_CORE_DUMPED".to_string(), "1".to_string());
+            }
+        }
+        env
+    }
+}
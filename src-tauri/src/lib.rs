@@ -1,74 +1,201 @@
 // DevBoot - GitBash Management App
 // Main library entry point
 
+mod api;
+mod cli;
 mod commands;
 mod config;
+mod cron;
+mod daemon;
+mod dependency;
 mod detector;
+mod env_file;
+mod events;
+mod health;
+mod hooks;
+mod limits;
+mod log_search;
+mod log_store;
+mod process_group;
 mod process_manager;
+mod pty;
+mod resources;
+mod scheduler;
+mod shell;
 mod startup;
+mod watcher;
 
 use commands::AppState;
 use tauri::Manager;
 
+/// Start sampling, auto-start projects, scheduling, file watching and,
+/// if enabled, the HTTP API.
+/// Shared by the window and `devbootd`.
+fn start_supervision(state: &AppState) {
+    state.process_manager.start_resource_sampler();
+
+    // Auto-start projects that have auto_start enabled
+    // in dependency order, pulling in whatever they depend on
+    let config = state.config.lock().unwrap();
+    state.process_manager.set_default_shell(config.settings.default_shell);
+    state.process_manager.set_log_settings(config.settings.logs.clone());
+    let api_settings = config.settings.api.clone();
+    let projects_to_start: Vec<_> = config.projects
+        .iter()
+        .filter(|p| p.auto_start && p.enabled && scheduler::in_active_window(p))
+        .cloned()
+        .collect();
+    let ids: Vec<&str> = projects_to_start.iter().map(|p| p.id.as_str()).collect();
+    // A hand-edited config may still contain a cycle; start unordered then
+    let plan = dependency::start_order(&config.projects, &ids)
+        .unwrap_or_else(|_| projects_to_start.clone());
+    drop(config);
+
+    state.process_manager.start_in_order(plan);
+    scheduler::start(state.config.clone(), state.process_manager.clone());
+    watcher::start(state.config.clone(), state.process_manager.clone());
+    if api_settings.enabled {
+        api::start(state.clone(), api_settings.port);
+    }
+}
+
+/// Entry point of `devbootd`: supervise the configured projects without a
+/// window, controlled through the control socket (see `daemon`)
+pub fn run_daemon() {
+    let mut args = std::env::args().skip(1);
+    let mut socket = daemon::socket_path();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--socket" => match args.next() {
+                Some(path) => socket = path.into(),
+                None => {
+                    eprintln!("devbootd: --socket needs a path");
+                    std::process::exit(2);
+                }
+            },
+            "-h" | "--help" => {
+                println!("Usage: devbootd [--socket <path>]");
+                return;
+            }
+            other => {
+                eprintln!("devbootd: unknown argument '{}'", other);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // Bind first so a second daemon exits before starting any project
+    let listener = match daemon::bind(&socket) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("devbootd: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let state = std::sync::Arc::new(AppState::new());
+    start_supervision(&state);
+    daemon::run(state, listener, &socket);
+}
+
+/// Entry point of `devboot-cli`
+pub fn run_cli() {
+    std::process::exit(cli::run(std::env::args().skip(1).collect()));
+}
+
+/// Commands the window calls through `invoke`
+fn local_commands() -> impl Fn(tauri::ipc::Invoke) -> bool + Send + Sync + 'static {
+    tauri::generate_handler![
+        // Config commands
+        commands::get_config,
+        commands::save_config_cmd,
+        commands::get_projects,
+        commands::add_project,
+        commands::update_project,
+        commands::delete_project,
+        commands::get_settings,
+        commands::update_settings,
+        // Process commands
+        commands::start_project,
+        commands::stop_project,
+        commands::restart_project,
+        commands::start_service,
+        commands::stop_service,
+        commands::restart_service,
+        commands::get_service_statuses,
+        commands::get_project_status,
+        commands::get_project_logs,
+        commands::get_log_page,
+        commands::search_logs,
+        commands::get_project_stats,
+        commands::get_breaker_status,
+        commands::clear_project_logs,
+        commands::send_project_input,
+        commands::send_project_interrupt,
+        commands::send_project_control,
+        commands::resize_project_terminal,
+        commands::stop_all_projects,
+        // Startup commands
+        commands::enable_auto_start,
+        commands::disable_auto_start,
+        commands::is_auto_start_enabled,
+        // Detection commands
+        commands::detect_project_from_path,
+    ]
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // When devbootd already supervises the projects, the window becomes its
+    // client instead of starting them a second time. Otherwise it supervises
+    // them and lets the CLI reach it over the socket.
+    let (listener, remote) = {
+        let socket = daemon::socket_path();
+        if daemon::is_running(&socket) {
+            (None, Some(std::sync::Arc::new(daemon::Remote::new(socket))))
+        } else {
+            match daemon::bind(&socket) {
+                Ok(listener) => (Some(listener), None),
+                Err(e) => {
+                    eprintln!("Control socket unavailable: {}", e);
+                    (None, None)
+                }
+            }
+        }
+    };
+
+    let local = local_commands();
+    let handler = {
+        let remote = remote.clone();
+        move |invoke: tauri::ipc::Invoke| match &remote {
+            Some(remote) => remote.invoke(invoke),
+            None => local(invoke),
+        }
+    };
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
-        .manage(AppState::new())
-        .setup(|app| {
+        .setup(move |app| {
+            if let Some(remote) = remote {
+                remote.relay_events(app.handle().clone());
+                return Ok(());
+            }
+
+            // Only a supervising window loads the config and keeps a
+            // process manager; a client of devbootd forwards every command
+            let state = AppState::new();
             // Inject app handle into process manager for event emission
-            let state = app.state::<AppState>();
             state.process_manager.set_app_handle(app.handle().clone());
-            
-            // Auto-start projects that have auto_start enabled
-            let config = state.config.lock().unwrap();
-            let projects_to_start: Vec<_> = config.projects
-                .iter()
-                .filter(|p| p.auto_start && p.enabled)
-                .cloned()
-                .collect();
-            drop(config);
+            start_supervision(&state);
 
-            for project in projects_to_start {
-                let _ = state.process_manager.start_project(
-                    &project.id,
-                    &project.path,
-                    &project.commands,
-                    project.restart_on_crash,
-                );
+            if let Some(listener) = listener {
+                daemon::serve(std::sync::Arc::new(state.clone()), listener);
             }
-
+            app.manage(state);
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![
-            // Config commands
-            commands::get_config,
-            commands::save_config_cmd,
-            commands::get_projects,
-            commands::add_project,
-            commands::update_project,
-            commands::delete_project,
-            commands::get_settings,
-            commands::update_settings,
-            // Process commands
-            commands::start_project,
-            commands::stop_project,
-            commands::restart_project,
-            commands::get_project_status,
-            commands::get_project_logs,
-            commands::clear_project_logs,
-            commands::send_project_input,
-            commands::send_project_interrupt,
-            commands::stop_all_projects,
-            // Startup commands
-            commands::enable_auto_start,
-            commands::disable_auto_start,
-            commands::is_auto_start_enabled,
-            // Detection commands
-            commands::detect_project_from_path,
-        ])
+        .invoke_handler(handler)
         .run(tauri::generate_context!())
         .expect("error while running DevBoot");
 }
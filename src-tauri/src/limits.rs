@@ -0,0 +1,94 @@
+//! Resource limits for projects
+//! Applies spawn-time limits and checks the ones the watchdog enforces
+
+use std::process::Command;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::config::ResourceLimits;
+use crate::resources::TreeUsage;
+
+/// A limit checked while a project runs
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitKind {
+    Memory,
+    Runtime,
+}
+
+/// A limit the watchdog found exceeded
+#[derive(Debug, Clone)]
+pub struct Violation {
+    pub kind: LimitKind,
+    pub message: String,
+}
+
+/// Set the open file limit and niceness in the child before it executes.
+///
+/// The open file limit lowers both the soft and hard limit, capped at the
+/// current hard limit. Niceness that needs privileges the app does not
+/// have is skipped rather than failing the spawn.
+pub fn apply(command: &mut Command, limits: &ResourceLimits) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+
+        let max_open_files = limits.max_open_files;
+        let nice = limits.nice;
+        if max_open_files.is_none() && nice.is_none() {
+            return;
+        }
+
+        unsafe {
+            command.pre_exec(move || {
+                if let Some(max) = max_open_files {
+                    let mut current = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+                    if libc::getrlimit(libc::RLIMIT_NOFILE, &mut current) == -1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    let value = (max as libc::rlim_t).min(current.rlim_max);
+                    let limit = libc::rlimit { rlim_cur: value, rlim_max: value };
+                    if libc::setrlimit(libc::RLIMIT_NOFILE, &limit) == -1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                if let Some(nice) = nice {
+                    libc::setpriority(libc::PRIO_PROCESS, 0, nice.clamp(-20, 19));
+                }
+                Ok(())
+            });
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (command, limits);
+    }
+}
+
+/// Check the memory and runtime limits of a run.
+/// `usage` is `None` where process trees cannot be sampled.
+pub fn check(limits: &ResourceLimits, usage: Option<&TreeUsage>, uptime: Duration) -> Vec<Violation> {
+    let mut violations = Vec::new();
+
+    if let (Some(max_mb), Some(usage)) = (limits.max_memory_mb, usage) {
+        let used_mb = usage.rss_bytes / (1024 * 1024);
+        if used_mb > max_mb {
+            violations.push(Violation {
+                kind: LimitKind::Memory,
+                message: format!("Memory limit exceeded: {} MB used, limit {} MB", used_mb, max_mb),
+            });
+        }
+    }
+
+    if let Some(max_secs) = limits.max_runtime_secs {
+        if uptime.as_secs() >= max_secs {
+            violations.push(Violation {
+                kind: LimitKind::Runtime,
+                message: format!("Runtime limit exceeded: ran for {}s, limit {}s", uptime.as_secs(), max_secs),
+            });
+        }
+    }
+
+    violations
+}
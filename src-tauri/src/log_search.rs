@@ -0,0 +1,366 @@
+//! Log search
+//! Scans the logs of any number of units, in memory and on disk, for
+//! lines matching a regex or plain text, returning each match with the
+//! lines around it.
+
+use std::collections::VecDeque;
+use std::time::SystemTime;
+
+use chrono::{DateTime, FixedOffset};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Project;
+use crate::dependency;
+use crate::process_manager::{LogEntry, LogStream, UnitRef};
+
+fn default_context() -> usize {
+    2
+}
+
+fn default_limit() -> usize {
+    100
+}
+
+/// What to search for, as passed to `search_logs`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogQuery {
+    pub pattern: String,
+    /// Treat `pattern` as a regex instead of plain text
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// Ids or names of the projects to search, with all their services;
+    /// all projects when empty
+    #[serde(default)]
+    pub project_ids: Vec<String>,
+    /// All streams when empty
+    #[serde(default)]
+    pub streams: Vec<LogStream>,
+    /// RFC 3339
+    #[serde(default)]
+    pub since: Option<String>,
+    /// RFC 3339
+    #[serde(default)]
+    pub until: Option<String>,
+    /// Lines returned before and after each match
+    #[serde(default = "default_context")]
+    pub context: usize,
+    /// Most matches returned; the newest are kept
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+impl Default for LogQuery {
+    fn default() -> Self {
+        Self {
+            pattern: String::new(),
+            regex: false,
+            case_sensitive: false,
+            project_ids: Vec::new(),
+            streams: Vec::new(),
+            since: None,
+            until: None,
+            context: default_context(),
+            limit: default_limit(),
+        }
+    }
+}
+
+/// A matching line with its surroundings, which are not filtered
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogMatch {
+    #[serde(flatten)]
+    pub unit: UnitRef,
+    pub entry: LogEntry,
+    pub before: Vec<LogEntry>,
+    pub after: Vec<LogEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogSearch {
+    /// Oldest first
+    pub matches: Vec<LogMatch>,
+    /// Whether older matches were left out because of `limit`
+    pub truncated: bool,
+}
+
+/// A query ready to run
+pub struct Search {
+    query: LogQuery,
+    pattern: Regex,
+    since: Option<DateTime<FixedOffset>>,
+    until: Option<DateTime<FixedOffset>>,
+}
+
+impl Search {
+    pub fn new(query: LogQuery) -> Result<Self, String> {
+        let pattern = if query.regex {
+            query.pattern.clone()
+        } else {
+            regex::escape(&query.pattern)
+        };
+        let pattern = RegexBuilder::new(&pattern)
+            .case_insensitive(!query.case_sensitive)
+            .build()
+            .map_err(|e| format!("Invalid pattern: {}", e))?;
+        let since = query.since.as_deref().map(parse_time).transpose()?;
+        let until = query.until.as_deref().map(parse_time).transpose()?;
+        Ok(Self {
+            query,
+            pattern,
+            since,
+            until,
+        })
+    }
+
+    /// Older log files can be skipped
+    pub fn since(&self) -> Option<SystemTime> {
+        self.since.map(SystemTime::from)
+    }
+
+    /// Units of the projects to search, in config order
+    pub fn unit_ids(&self, projects: &[Project]) -> Result<Vec<String>, String> {
+        let selected: Vec<&Project> = if self.query.project_ids.is_empty() {
+            projects.iter().collect()
+        } else {
+            self.query
+                .project_ids
+                .iter()
+                .map(|id| {
+                    dependency::find_project(projects, id).ok_or_else(|| format!("Project not found: {}", id))
+                })
+                .collect::<Result<_, _>>()?
+        };
+        Ok(selected
+            .into_iter()
+            .flat_map(|project| project.units())
+            .map(|unit| unit.id)
+            .collect())
+    }
+
+    /// Search units, each given by its id and its entries oldest first
+    pub fn run<I>(&self, units: Vec<(String, I)>) -> LogSearch
+    where
+        I: Iterator<Item = LogEntry>,
+    {
+        let mut matches = Vec::new();
+        let mut truncated = false;
+        for (unit_id, entries) in units {
+            let (found, dropped) = self.scan(&unit_id, entries);
+            matches.extend(found);
+            truncated |= dropped;
+        }
+
+        // Keep the newest matches across all units
+        matches.sort_by_cached_key(|found| DateTime::parse_from_rfc3339(&found.entry.timestamp).ok());
+        if matches.len() > self.query.limit {
+            matches.drain(..matches.len() - self.query.limit);
+            truncated = true;
+        }
+        LogSearch { matches, truncated }
+    }
+
+    /// The last `limit` matches of one unit, and whether any were dropped
+    fn scan(&self, unit_id: &str, entries: impl Iterator<Item = LogEntry>) -> (VecDeque<LogMatch>, bool) {
+        let context = self.query.context;
+        let mut matches: VecDeque<LogMatch> = VecDeque::new();
+        let mut recent: VecDeque<LogEntry> = VecDeque::with_capacity(context + 1);
+        let mut dropped = false;
+
+        for entry in entries {
+            // Only the last few matches can still be waiting for lines after them
+            for found in matches.iter_mut().rev() {
+                if found.after.len() >= context {
+                    break;
+                }
+                found.after.push(entry.clone());
+            }
+
+            if self.matches(&entry) {
+                matches.push_back(LogMatch {
+                    unit: UnitRef::new(unit_id),
+                    entry: entry.clone(),
+                    before: recent.iter().cloned().collect(),
+                    after: Vec::new(),
+                });
+                if matches.len() > self.query.limit {
+                    matches.pop_front();
+                    dropped = true;
+                }
+            }
+
+            recent.push_back(entry);
+            if recent.len() > context {
+                recent.pop_front();
+            }
+        }
+        (matches, dropped)
+    }
+
+    fn matches(&self, entry: &LogEntry) -> bool {
+        if !self.query.streams.is_empty() && !self.query.streams.contains(&entry.stream) {
+            return false;
+        }
+        if self.since.is_some() || self.until.is_some() {
+            let Ok(time) = DateTime::parse_from_rfc3339(&entry.timestamp) else {
+                return false;
+            };
+            if self.since.is_some_and(|since| time < since) || self.until.is_some_and(|until| time > until) {
+                return false;
+            }
+        }
+        self.pattern.is_match(&entry.text)
+    }
+}
+
+fn parse_time(time: &str) -> Result<DateTime<FixedOffset>, String> {
+    DateTime::parse_from_rfc3339(time).map_err(|e| format!("Invalid time '{}': {}", time, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process_manager::LogLevel;
+
+    /// Line `seq` of a unit, logged `seq` seconds after 09:00
+    fn entry(seq: u64, stream: LogStream, text: &str) -> LogEntry {
+        LogEntry {
+            seq,
+            timestamp: format!("2024-05-01T09:00:{:02}+00:00", seq),
+            stream,
+            level: LogLevel::Info,
+            run_id: 1,
+            text: text.to_string(),
+        }
+    }
+
+    /// Lines 0 to 9 reading `line <n>`, with `hit` appended to `hits`
+    fn lines(hits: &[u64]) -> Vec<LogEntry> {
+        (0..10)
+            .map(|seq| {
+                let text = match hits.contains(&seq) {
+                    true => format!("line {} hit", seq),
+                    false => format!("line {}", seq),
+                };
+                entry(seq, LogStream::Stdout, &text)
+            })
+            .collect()
+    }
+
+    fn search(query: LogQuery) -> Search {
+        Search::new(query).unwrap()
+    }
+
+    fn query(pattern: &str) -> LogQuery {
+        LogQuery {
+            pattern: pattern.to_string(),
+            ..LogQuery::default()
+        }
+    }
+
+    fn seqs(entries: &[LogEntry]) -> Vec<u64> {
+        entries.iter().map(|entry| entry.seq).collect()
+    }
+
+    #[test]
+    fn matches_carry_context_even_when_they_overlap() {
+        let (found, dropped) = search(query("hit")).scan("p", lines(&[0, 3, 4, 9]).into_iter());
+        assert!(!dropped);
+        let found: Vec<(u64, Vec<u64>, Vec<u64>)> = found
+            .iter()
+            .map(|m| (m.entry.seq, seqs(&m.before), seqs(&m.after)))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (0, vec![], vec![1, 2]),
+                (3, vec![1, 2], vec![4, 5]),
+                (4, vec![2, 3], vec![5, 6]),
+                (9, vec![7, 8], vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn zero_context_returns_the_lines_alone() {
+        let search = search(LogQuery { context: 0, ..query("hit") });
+        let (found, _) = search.scan("p", lines(&[5]).into_iter());
+        assert_eq!(found.len(), 1);
+        assert!(found[0].before.is_empty() && found[0].after.is_empty());
+    }
+
+    #[test]
+    fn limit_keeps_the_newest_matches() {
+        let search = search(LogQuery { limit: 2, ..query("hit") });
+        let (found, dropped) = search.scan("p", lines(&[1, 2, 6, 8]).into_iter());
+        assert!(dropped);
+        assert_eq!(found.iter().map(|m| m.entry.seq).collect::<Vec<_>>(), [6, 8]);
+        // The kept matches still get their context
+        assert_eq!(seqs(&found[0].after), [7, 8]);
+    }
+
+    #[test]
+    fn run_merges_units_by_time_and_limits_overall() {
+        let search = search(LogQuery { limit: 3, ..query("hit") });
+        let result = search.run(vec![
+            ("a".to_string(), lines(&[1, 7]).into_iter()),
+            ("b/api".to_string(), lines(&[4, 9]).into_iter()),
+        ]);
+        assert!(result.truncated);
+        let found: Vec<(&str, Option<&str>, u64)> = result
+            .matches
+            .iter()
+            .map(|m| (m.unit.project_id.as_str(), m.unit.service.as_deref(), m.entry.seq))
+            .collect();
+        assert_eq!(found, [("b", Some("api"), 4), ("a", None, 7), ("b", Some("api"), 9)]);
+    }
+
+    #[test]
+    fn plain_text_is_literal_and_ignores_case_by_default() {
+        let entries = || {
+            vec![
+                entry(0, LogStream::Stdout, "GET /a.b"),
+                entry(1, LogStream::Stdout, "get /aXb"),
+            ]
+            .into_iter()
+        };
+        assert_eq!(search(query("get /a.b")).scan("p", entries()).0.len(), 1);
+        assert_eq!(search(LogQuery { regex: true, ..query("get /a.b") }).scan("p", entries()).0.len(), 2);
+        let case_sensitive = LogQuery { regex: true, case_sensitive: true, ..query("get /a.b") };
+        assert_eq!(search(case_sensitive).scan("p", entries()).0.len(), 1);
+    }
+
+    #[test]
+    fn filters_by_stream_and_time() {
+        let entries = || {
+            vec![
+                entry(1, LogStream::Stdout, "hit"),
+                entry(2, LogStream::Stderr, "hit"),
+                entry(3, LogStream::System, "hit"),
+                entry(4, LogStream::Stderr, "hit"),
+            ]
+            .into_iter()
+        };
+        let stderr = search(LogQuery { streams: vec![LogStream::Stderr], ..query("hit") });
+        let found: Vec<u64> = stderr.scan("p", entries()).0.iter().map(|m| m.entry.seq).collect();
+        assert_eq!(found, [2, 4]);
+
+        let window = search(LogQuery {
+            since: Some("2024-05-01T11:00:02+02:00".to_string()),
+            until: Some("2024-05-01T09:00:03Z".to_string()),
+            ..query("hit")
+        });
+        let found: Vec<u64> = window.scan("p", entries()).0.iter().map(|m| m.entry.seq).collect();
+        assert_eq!(found, [2, 3]);
+    }
+
+    #[test]
+    fn rejects_invalid_patterns_and_times() {
+        let error = |query| Search::new(query).err().unwrap();
+        assert!(error(LogQuery { regex: true, ..query("(") }).starts_with("Invalid pattern:"));
+        let since = LogQuery { since: Some("yesterday".to_string()), ..query("x") };
+        assert!(error(since).starts_with("Invalid time 'yesterday'"));
+    }
+}
@@ -0,0 +1,655 @@
+//! Persistent project logs
+//! Every log entry is also appended to `output.log` in
+//! `logs/<project id>[/<service>]` in the config directory as a line of
+//! JSON, by a writer thread of its own. Files are rotated by size and age, optionally
+//! gzipped, and pruned by age and a quota shared by all projects.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::config::{self, LogSettings};
+use crate::process_manager::LogEntry;
+
+/// The file currently written to; rotated files are `output-<time>.log[.gz]`
+const CURRENT_FILE: &str = "output.log";
+const MB: u64 = 1024 * 1024;
+
+#[derive(Debug)]
+enum Message {
+    Append { unit_id: String, entry: LogEntry },
+    /// Apply retention and the quota, e.g. after the settings changed
+    Prune,
+}
+
+/// Handle to the log writer. Cloning is cheap and yields a handle to the
+/// same writer.
+#[derive(Debug, Clone)]
+pub struct LogStore {
+    root: PathBuf,
+    settings: Arc<Mutex<LogSettings>>,
+    sender: mpsc::Sender<Message>,
+}
+
+/// `logs` next to `config.json`
+pub fn logs_dir() -> PathBuf {
+    config::get_config_path().with_file_name("logs")
+}
+
+impl LogStore {
+    /// Start a writer for `logs_dir()`
+    pub fn new() -> Self {
+        let root = logs_dir();
+        let settings = Arc::new(Mutex::new(LogSettings::default()));
+        let (sender, receiver) = mpsc::channel();
+        let writer = Writer {
+            root: root.clone(),
+            settings: Arc::clone(&settings),
+            open: HashMap::new(),
+            warned: false,
+        };
+        thread::spawn(move || writer.run(receiver));
+        Self { root, settings, sender }
+    }
+
+    pub fn configure(&self, settings: LogSettings) {
+        *self.settings.lock().unwrap() = settings;
+        let _ = self.sender.send(Message::Prune);
+    }
+
+    /// Queue an entry for the unit's current file
+    pub fn append(&self, unit_id: &str, entry: &LogEntry) {
+        let _ = self.sender.send(Message::Append {
+            unit_id: unit_id.to_string(),
+            entry: entry.clone(),
+        });
+    }
+
+    /// The last `limit` entries on disk for a unit, e.g. from the last session
+    pub fn tail(&self, unit_id: &str, limit: usize) -> Vec<LogEntry> {
+        if !self.settings.lock().unwrap().persist {
+            return Vec::new();
+        }
+        read_tail(&self.root, unit_id, limit)
+    }
+
+    /// Every entry on disk for a unit, see `read_all`
+    pub fn entries(&self, unit_id: &str, since: Option<SystemTime>) -> Box<dyn Iterator<Item = LogEntry>> {
+        if !self.settings.lock().unwrap().persist {
+            return Box::new(std::iter::empty());
+        }
+        Box::new(read_all(&self.root, unit_id, since))
+    }
+}
+
+impl Default for LogStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The last `limit` entries logged for a unit under `root`, oldest first.
+/// Reads into the newest rotated file when the current one is shorter.
+/// Lines that are not entries, e.g. plain text from an older version, are
+/// skipped.
+pub fn read_tail(root: &Path, unit_id: &str, limit: usize) -> Vec<LogEntry> {
+    let dir = unit_dir(root, unit_id);
+    let mut entries = parse_entries(tail_of_file(&dir.join(CURRENT_FILE), limit).unwrap_or_default());
+    if entries.len() < limit {
+        if let Some(rotated) = rotated_files(&dir).pop() {
+            let mut older = parse_entries(read_rotated(&rotated.path, limit - entries.len()));
+            older.append(&mut entries);
+            entries = older;
+        }
+    }
+    entries
+}
+
+/// Every entry logged for a unit under `root`, oldest first, read lazily.
+/// Rotated files last written before `since` are skipped.
+pub fn read_all(root: &Path, unit_id: &str, since: Option<SystemTime>) -> impl Iterator<Item = LogEntry> {
+    let dir = unit_dir(root, unit_id);
+    let mut paths: Vec<PathBuf> = rotated_files(&dir)
+        .into_iter()
+        .filter(|file| since.is_none_or(|since| file.modified >= since))
+        .map(|file| file.path)
+        .collect();
+    paths.push(dir.join(CURRENT_FILE));
+    paths
+        .into_iter()
+        .flat_map(read_lines)
+        .filter_map(|line| serde_json::from_slice(&line).ok())
+}
+
+/// Lines of one log file up to its first read error, e.g. in a gzip file
+/// truncated by a crash. The error is reported and ends only this file, so
+/// the files after it are still read.
+fn read_lines(path: PathBuf) -> impl Iterator<Item = Vec<u8>> {
+    let reader = match open_log(&path) {
+        Ok(reader) => Some(reader),
+        Err(e) => {
+            // The current file does not exist until the unit logs something
+            if e.kind() != io::ErrorKind::NotFound {
+                eprintln!("Failed to read '{}': {}", path.display(), e);
+            }
+            None
+        }
+    };
+    reader
+        .into_iter()
+        .flat_map(|reader| reader.split(b'\n'))
+        .map_while(move |line| match line {
+            Ok(line) => Some(line),
+            Err(e) => {
+                eprintln!("Failed to read '{}': {}", path.display(), e);
+                None
+            }
+        })
+}
+
+fn parse_entries(lines: Vec<String>) -> Vec<LogEntry> {
+    lines
+        .iter()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// `<root>/<project id>` for a project, `<root>/<project id>/<service>`
+/// for a service
+fn unit_dir(root: &Path, unit_id: &str) -> PathBuf {
+    let (project_id, service) = config::split_unit_id(unit_id);
+    let mut dir = root.join(file_name(project_id));
+    if let Some(service) = service {
+        dir.push(file_name(service));
+    }
+    dir
+}
+
+/// Ids and service names from a hand-edited config may contain anything
+fn file_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || "-_ ".contains(c) { c } else { '_' })
+        .collect();
+    if name.is_empty() {
+        "_".to_string()
+    } else {
+        name
+    }
+}
+
+/// Last `limit` lines of a plain file, reading it backwards in chunks
+fn tail_of_file(path: &Path, limit: usize) -> io::Result<Vec<String>> {
+    const CHUNK: u64 = 64 * 1024;
+    let mut file = File::open(path)?;
+    let mut start = file.metadata()?.len();
+    let mut buffer = Vec::new();
+    let mut newlines = 0;
+    // One newline more than `limit` guarantees the first line read is whole
+    while start > 0 && newlines <= limit {
+        let size = CHUNK.min(start);
+        start -= size;
+        file.seek(SeekFrom::Start(start))?;
+        let mut chunk = vec![0; size as usize];
+        file.read_exact(&mut chunk)?;
+        newlines += chunk.iter().filter(|&&b| b == b'\n').count();
+        chunk.extend_from_slice(&buffer);
+        buffer = chunk;
+    }
+    let text = String::from_utf8_lossy(&buffer);
+    let lines: Vec<&str> = text.lines().collect();
+    Ok(lines[lines.len().saturating_sub(limit)..].iter().map(|line| line.to_string()).collect())
+}
+
+/// A log file for reading, decompressing it if it is gzipped
+fn open_log(path: &Path) -> io::Result<Box<dyn BufRead>> {
+    let file = File::open(path)?;
+    Ok(if path.extension().is_some_and(|ext| ext == "gz") {
+        Box::new(BufReader::new(GzDecoder::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    })
+}
+
+/// Last `limit` lines of a rotated file, which may be gzipped, up to its
+/// first read error
+fn read_rotated(path: &Path, limit: usize) -> Vec<String> {
+    let mut lines = VecDeque::with_capacity(limit);
+    for line in read_lines(path.to_path_buf()) {
+        if lines.len() == limit {
+            lines.pop_front();
+        }
+        lines.push_back(String::from_utf8_lossy(&line).into_owned());
+    }
+    lines.into()
+}
+
+struct LogFile {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+/// Rotated files of one unit, oldest first. Of a file and its gzipped
+/// copy, left together by a compression that did not finish deleting the
+/// original, only the copy is listed.
+fn rotated_files(dir: &Path) -> Vec<LogFile> {
+    let entries: Vec<fs::DirEntry> = fs::read_dir(dir).into_iter().flatten().flatten().collect();
+    let names: HashSet<String> = entries
+        .iter()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    let mut files: Vec<LogFile> = entries
+        .into_iter()
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            is_rotated(&name) && !names.contains(&format!("{}.gz", name))
+        })
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some(LogFile {
+                path: entry.path(),
+                size: metadata.len(),
+                modified: metadata.modified().ok()?,
+            })
+        })
+        .collect();
+    files.sort_by_key(|file| file.modified);
+    files
+}
+
+fn is_rotated(name: &str) -> bool {
+    name.starts_with("output-") && (name.ends_with(".log") || name.ends_with(".log.gz"))
+}
+
+/// The current file of a unit, open for appending
+struct OpenLog {
+    writer: BufWriter<File>,
+    size: u64,
+    created: SystemTime,
+}
+
+struct Writer {
+    root: PathBuf,
+    settings: Arc<Mutex<LogSettings>>,
+    open: HashMap<String, OpenLog>,
+    /// Only the first write error is reported
+    warned: bool,
+}
+
+impl Writer {
+    /// Write lines as they arrive, flushing once the queue is drained
+    fn run(mut self, receiver: mpsc::Receiver<Message>) {
+        while let Ok(message) = receiver.recv() {
+            let settings = self.settings.lock().unwrap().clone();
+            self.handle(message, &settings);
+            while let Ok(message) = receiver.try_recv() {
+                self.handle(message, &settings);
+            }
+            for log in self.open.values_mut() {
+                let _ = log.writer.flush();
+            }
+        }
+    }
+
+    fn handle(&mut self, message: Message, settings: &LogSettings) {
+        match message {
+            Message::Append { unit_id, entry } => {
+                if !settings.persist {
+                    // Files are reopened once persisting is turned back on
+                    self.open.clear();
+                    return;
+                }
+                let line = match serde_json::to_string(&entry) {
+                    Ok(line) => line,
+                    Err(_) => return,
+                };
+                if let Err(e) = self.append(&unit_id, &line, settings) {
+                    if !self.warned {
+                        eprintln!("Failed to write log of {}: {}", unit_id, e);
+                        self.warned = true;
+                    }
+                }
+            }
+            Message::Prune => self.prune(settings),
+        }
+    }
+
+    fn append(&mut self, unit_id: &str, line: &str, settings: &LogSettings) -> io::Result<()> {
+        let dir = unit_dir(&self.root, unit_id);
+        if !self.open.contains_key(unit_id) {
+            self.open.insert(unit_id.to_string(), open_current(&dir)?);
+        }
+
+        let log = &self.open[unit_id];
+        let max_size = settings.max_file_mb.max(1) * MB;
+        let max_age = Duration::from_secs(settings.max_file_hours.max(1) * 3600);
+        let age = log.created.elapsed().unwrap_or_default();
+        if log.size > 0 && (log.size + line.len() as u64 + 1 > max_size || age >= max_age) {
+            self.rotate(unit_id, &dir, settings)?;
+            self.open.insert(unit_id.to_string(), open_current(&dir)?);
+        }
+
+        let log = self.open.get_mut(unit_id).expect("opened above");
+        log.writer.write_all(line.as_bytes())?;
+        log.writer.write_all(b"\n")?;
+        log.size += line.len() as u64 + 1;
+        self.warned = false;
+        Ok(())
+    }
+
+    /// Move the current file aside, compress it if configured and prune
+    fn rotate(&mut self, unit_id: &str, dir: &Path, settings: &LogSettings) -> io::Result<()> {
+        if let Some(mut log) = self.open.remove(unit_id) {
+            log.writer.flush()?;
+        }
+        let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+        let mut rotated = dir.join(format!("output-{}.log", stamp));
+        let mut n = 1;
+        while rotated.exists() || rotated.with_extension("log.gz").exists() {
+            rotated = dir.join(format!("output-{}-{}.log", stamp, n));
+            n += 1;
+        }
+        fs::rename(dir.join(CURRENT_FILE), &rotated)?;
+
+        if settings.compress {
+            if let Err(e) = compress(&rotated) {
+                eprintln!("Failed to compress '{}': {}", rotated.display(), e);
+            }
+        }
+        self.prune(settings);
+        Ok(())
+    }
+
+    /// Delete rotated files past retention, then the oldest ones until all
+    /// logs fit the quota. Current files are never deleted. Also finishes
+    /// compressions interrupted by a crash.
+    fn prune(&self, settings: &LogSettings) {
+        let mut rotated = Vec::new();
+        let mut total = 0;
+        for dir in unit_dirs(&self.root) {
+            finish_compressions(&dir);
+            total += fs::metadata(dir.join(CURRENT_FILE)).map(|m| m.len()).unwrap_or(0);
+            rotated.extend(rotated_files(&dir));
+        }
+
+        let retention = Duration::from_secs(settings.retention_days * 24 * 3600);
+        let now = SystemTime::now();
+        rotated.retain(|file| {
+            let expired = now.duration_since(file.modified).unwrap_or_default() > retention;
+            if expired && fs::remove_file(&file.path).is_ok() {
+                return false;
+            }
+            total += file.size;
+            true
+        });
+
+        rotated.sort_by_key(|file| file.modified);
+        let quota = settings.max_total_mb * MB;
+        for file in rotated {
+            if total <= quota {
+                break;
+            }
+            if fs::remove_file(&file.path).is_ok() {
+                total -= file.size;
+            }
+        }
+    }
+}
+
+fn open_current(dir: &Path) -> io::Result<OpenLog> {
+    fs::create_dir_all(dir)?;
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(CURRENT_FILE))?;
+    let metadata = file.metadata()?;
+    Ok(OpenLog {
+        size: metadata.len(),
+        // Without creation times, an existing file's age counts from now
+        created: metadata.created().unwrap_or_else(|_| SystemTime::now()),
+        writer: BufWriter::new(file),
+    })
+}
+
+/// Replace `path` with a gzipped `<path>.gz`. The copy is written under a
+/// temporary name first, so readers never see a partial one.
+fn compress(path: &Path) -> io::Result<()> {
+    let partial = path.with_extension("log.gz.tmp");
+    let mut input = File::open(path)?;
+    let mut encoder = GzEncoder::new(File::create(&partial)?, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    fs::rename(&partial, path.with_extension("log.gz"))?;
+    fs::remove_file(path)
+}
+
+/// Clean up after compressions interrupted by a crash: delete partial
+/// copies, and originals whose copy was finished
+fn finish_compressions(dir: &Path) {
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let done = is_rotated(&name) && name.ends_with(".log") && path.with_extension("log.gz").exists();
+        if done || (name.starts_with("output-") && name.ends_with(".log.gz.tmp")) {
+            let _ = fs::remove_file(&path);
+        }
+    }
+}
+
+/// Every directory holding a unit's logs: projects and their services
+fn unit_dirs(root: &Path) -> Vec<PathBuf> {
+    let subdirs = |dir: &Path| -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+            .map(|entry| entry.path())
+            .collect()
+    };
+    let mut dirs = Vec::new();
+    for project in subdirs(root) {
+        dirs.extend(subdirs(&project));
+        dirs.push(project);
+    }
+    dirs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process_manager::{LogLevel, LogStream};
+
+    /// An empty directory of its own for each test
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("devboot-logs-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    fn entry(seq: u64, text: &str) -> LogEntry {
+        LogEntry {
+            seq,
+            timestamp: "2024-05-01T09:00:00+00:00".to_string(),
+            stream: LogStream::Stdout,
+            level: LogLevel::Info,
+            run_id: 1,
+            text: text.to_string(),
+        }
+    }
+
+    /// Write entries `seqs` to `path`, gzipped for `.gz`, last modified
+    /// `age` ago
+    fn write_log(path: &Path, seqs: &[u64], age: Duration) {
+        let mut content = String::new();
+        for &seq in seqs {
+            content.push_str(&serde_json::to_string(&entry(seq, "line")).unwrap());
+            content.push('\n');
+        }
+        write_file(path, content.as_bytes(), age);
+    }
+
+    fn write_file(path: &Path, content: &[u8], age: Duration) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        if path.extension().is_some_and(|ext| ext == "gz") {
+            let mut encoder = GzEncoder::new(File::create(path).unwrap(), Compression::default());
+            encoder.write_all(content).unwrap();
+            encoder.finish().unwrap();
+        } else {
+            fs::write(path, content).unwrap();
+        }
+        set_age(path, age);
+    }
+
+    fn set_age(path: &Path, age: Duration) {
+        let file = File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - age).unwrap();
+    }
+
+    fn hours(hours: u64) -> Duration {
+        Duration::from_secs(hours * 3600)
+    }
+
+    fn writer(root: &Path) -> Writer {
+        Writer {
+            root: root.to_path_buf(),
+            settings: Arc::new(Mutex::new(LogSettings::default())),
+            open: HashMap::new(),
+            warned: false,
+        }
+    }
+
+    fn seqs(entries: impl Iterator<Item = LogEntry>) -> Vec<u64> {
+        entries.map(|entry| entry.seq).collect()
+    }
+
+    fn names(files: &[LogFile]) -> Vec<String> {
+        files
+            .iter()
+            .map(|file| file.path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn rotates_the_current_file_at_the_size_cap() {
+        let root = temp_root("rotate");
+        let settings = LogSettings { max_file_mb: 1, ..LogSettings::default() };
+        let mut writer = writer(&root);
+        let big = "x".repeat(600 * 1024);
+        for (seq, text) in [(0, big.as_str()), (1, big.as_str()), (2, "small")] {
+            let line = serde_json::to_string(&entry(seq, text)).unwrap();
+            writer.append("p", &line, &settings).unwrap();
+        }
+        drop(writer);
+
+        let dir = unit_dir(&root, "p");
+        let rotated = rotated_files(&dir);
+        assert_eq!(rotated.len(), 1);
+        assert_eq!(seqs(read_all(&root, "p", None)), [0, 1, 2]);
+        assert_eq!(seqs(read_tail(&root, "p", 2).into_iter()), [1, 2]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn reads_gzipped_and_plain_files_oldest_first() {
+        let root = temp_root("order");
+        let dir = unit_dir(&root, "p/web");
+        write_log(&dir.join("output-a.log.gz"), &[0, 1], hours(3));
+        write_log(&dir.join("output-b.log"), &[2, 3], hours(2));
+        write_log(&dir.join("output-c.log.gz"), &[4], hours(1));
+        write_log(&dir.join(CURRENT_FILE), &[5], Duration::ZERO);
+
+        assert_eq!(seqs(read_all(&root, "p/web", None)), [0, 1, 2, 3, 4, 5]);
+        let since = SystemTime::now() - Duration::from_secs(90 * 60);
+        assert_eq!(seqs(read_all(&root, "p/web", Some(since))), [4, 5]);
+        // The tail reads into the newest rotated file only
+        assert_eq!(seqs(read_tail(&root, "p/web", 10).into_iter()), [4, 5]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn a_damaged_file_does_not_hide_the_files_after_it() {
+        let root = temp_root("damaged");
+        let dir = unit_dir(&root, "p");
+        let gz = dir.join("output-a.log.gz");
+        write_log(&gz, &[0, 1, 2, 3], hours(2));
+        let bytes = fs::read(&gz).unwrap();
+        fs::write(&gz, &bytes[..bytes.len() / 2]).unwrap();
+        set_age(&gz, hours(2));
+        write_log(&dir.join("output-b.log"), &[4], hours(1));
+        write_log(&dir.join(CURRENT_FILE), &[5], Duration::ZERO);
+
+        assert!(seqs(read_all(&root, "p", None)).ends_with(&[4, 5]));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn an_interrupted_compression_is_read_once_and_finished() {
+        let root = temp_root("compress");
+        let dir = unit_dir(&root, "p");
+        write_log(&dir.join("output-a.log"), &[0], hours(3));
+        write_log(&dir.join("output-a.log.gz"), &[0], hours(3));
+        write_file(&dir.join("output-b.log.gz.tmp"), b"partial", hours(2));
+        write_log(&dir.join("output-c.log"), &[1], hours(1));
+
+        assert_eq!(seqs(read_all(&root, "p", None)), [0, 1]);
+        writer(&root).prune(&LogSettings::default());
+        let mut left: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["output-a.log.gz", "output-c.log"]);
+
+        compress(&dir.join("output-c.log")).unwrap();
+        assert_eq!(names(&rotated_files(&dir)), ["output-a.log.gz", "output-c.log.gz"]);
+        assert_eq!(seqs(read_all(&root, "p", None)), [0, 1]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn retention_deletes_expired_rotated_files_only() {
+        let root = temp_root("retention");
+        let dir = unit_dir(&root, "p");
+        write_log(&dir.join("output-a.log.gz"), &[0], hours(72));
+        write_log(&dir.join("output-b.log"), &[1], hours(48));
+        write_log(&dir.join("output-c.log"), &[2], hours(1));
+        // The current file is kept however old it is
+        write_log(&dir.join(CURRENT_FILE), &[3], hours(96));
+
+        let settings = LogSettings { retention_days: 1, ..LogSettings::default() };
+        writer(&root).prune(&settings);
+        assert_eq!(names(&rotated_files(&dir)), ["output-c.log"]);
+        assert_eq!(seqs(read_all(&root, "p", None)), [2, 3]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn the_quota_deletes_the_oldest_files_of_any_unit_first() {
+        let root = temp_root("quota");
+        let project = unit_dir(&root, "p");
+        let service = unit_dir(&root, "q/api");
+        let chunk = vec![b'x'; 400 * 1024];
+        write_file(&service.join("output-a.log"), &chunk, hours(3));
+        write_file(&project.join("output-b.log"), &chunk, hours(2));
+        write_file(&service.join("output-c.log"), &chunk, hours(1));
+        write_file(&project.join(CURRENT_FILE), &chunk[..100 * 1024], Duration::ZERO);
+
+        let settings = LogSettings { max_total_mb: 1, ..LogSettings::default() };
+        writer(&root).prune(&settings);
+        assert_eq!(names(&rotated_files(&project)), ["output-b.log"]);
+        assert_eq!(names(&rotated_files(&service)), ["output-c.log"]);
+        assert!(project.join(CURRENT_FILE).exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
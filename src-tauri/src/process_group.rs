@@ -0,0 +1,271 @@
+//! Process group management
+//! Spawns each project in its own group and stops the whole tree gracefully
+
+use std::fmt;
+use std::process::{Command, ExitStatus};
+use std::time::{Duration, Instant};
+
+use tokio::process::Child;
+
+use crate::config::StopSignal;
+
+/// How a stop request ended the process tree
+#[derive(Debug, Clone)]
+pub enum StopOutcome {
+    /// The process had exited before the stop request
+    AlreadyExited,
+    /// Everything exited within the grace period after the stop signal
+    Exited { signal: &'static str, elapsed: Duration },
+    /// The grace period ran out and the remaining processes were force killed
+    Killed { signal: &'static str, grace: Duration },
+}
+
+impl fmt::Display for StopOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StopOutcome::AlreadyExited => write!(f, "Process had already exited"),
+            StopOutcome::Exited { signal, elapsed } => {
+                write!(f, "Stopped by {} after {:.1}s", signal, elapsed.as_secs_f64())
+            }
+            StopOutcome::Killed { signal, grace } => write!(
+                f,
+                "Did not exit within {:.1}s of {}, force killed",
+                grace.as_secs_f64(),
+                signal
+            ),
+        }
+    }
+}
+
+/// How a process ended: with an exit code, or (on Unix) by a signal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExitDetails {
+    pub code: Option<i32>,
+    pub signal: Option<i32>,
+    pub core_dumped: bool,
+}
+
+impl ExitDetails {
+    pub fn from_status(status: &ExitStatus) -> Self {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            Self {
+                code: status.code(),
+                signal: status.signal(),
+                core_dumped: status.core_dumped(),
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            Self {
+                code: status.code(),
+                signal: None,
+                core_dumped: false,
+            }
+        }
+    }
+
+    /// Name of the terminating signal, e.g. `SIGSEGV`
+    pub fn signal_name(&self) -> Option<String> {
+        self.signal.map(signal_name)
+    }
+}
+
+impl fmt::Display for ExitDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.code, self.signal_name()) {
+            (Some(code), _) => write!(f, "exit code {}", code)?,
+            (None, Some(signal)) => write!(f, "signal {}", signal)?,
+            (None, None) => write!(f, "unknown exit status")?,
+        }
+        if self.core_dumped {
+            write!(f, " (core dumped)")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn signal_name(signal: i32) -> String {
+    let name = match signal {
+        libc::SIGHUP => "SIGHUP",
+        libc::SIGINT => "SIGINT",
+        libc::SIGQUIT => "SIGQUIT",
+        libc::SIGILL => "SIGILL",
+        libc::SIGTRAP => "SIGTRAP",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGUSR1 => "SIGUSR1",
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGUSR2 => "SIGUSR2",
+        libc::SIGPIPE => "SIGPIPE",
+        libc::SIGALRM => "SIGALRM",
+        libc::SIGTERM => "SIGTERM",
+        libc::SIGXCPU => "SIGXCPU",
+        libc::SIGXFSZ => "SIGXFSZ",
+        libc::SIGSYS => "SIGSYS",
+        _ => return format!("signal {}", signal),
+    };
+    name.to_string()
+}
+
+#[cfg(not(unix))]
+fn signal_name(signal: i32) -> String {
+    format!("signal {}", signal)
+}
+
+/// Make the spawned process the leader of a new process group
+pub fn set_new_group(command: &mut Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = command;
+    }
+}
+
+/// Ask the process tree led by `pid` to stop, then force kill whatever is
+/// left after `grace`. `child` is the leader, which is reaped here.
+pub async fn terminate(child: &mut Child, pid: u32, signal: StopSignal, grace: Duration) -> StopOutcome {
+    let leader_exited = matches!(child.try_wait(), Ok(Some(_)));
+    if leader_exited && !group_alive(pid) {
+        return StopOutcome::AlreadyExited;
+    }
+
+    let signal_name = send_stop_signal(pid, signal);
+    let start = Instant::now();
+    let exited = tokio::time::timeout(grace, async {
+        let _ = child.wait().await;
+        while group_alive(pid) {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await;
+    if exited.is_ok() {
+        return StopOutcome::Exited {
+            signal: signal_name,
+            elapsed: start.elapsed(),
+        };
+    }
+
+    kill_tree(pid);
+    let _ = child.start_kill();
+    let _ = child.wait().await;
+    StopOutcome::Killed {
+        signal: signal_name,
+        grace,
+    }
+}
+
+/// Immediately kill the whole process tree led by `pid` without waiting
+pub fn kill_tree(pid: u32) {
+    #[cfg(unix)]
+    {
+        send_to_group(pid, libc::SIGKILL);
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        let _ = Command::new("taskkill")
+            .args(["/F", "/T", "/PID", &pid.to_string()])
+            .creation_flags(0x08000000) // CREATE_NO_WINDOW
+            .output();
+    }
+}
+
+#[cfg(unix)]
+fn send_stop_signal(pid: u32, signal: StopSignal) -> &'static str {
+    send_to_group(pid, signal.as_raw());
+    // Processes suspended with Ctrl+Z only handle the signal once continued
+    send_to_group(pid, libc::SIGCONT);
+    signal.name()
+}
+
+/// Send a signal to the whole process group, e.g. `SIGINT` for Ctrl+C
+#[cfg(unix)]
+pub fn signal_group(pid: u32, signal: libc::c_int) {
+    send_to_group(pid, signal);
+}
+
+/// Windows has no signals; ask the tree to close without /F first
+#[cfg(windows)]
+fn send_stop_signal(pid: u32, _signal: StopSignal) -> &'static str {
+    use std::os::windows::process::CommandExt;
+    let _ = Command::new("taskkill")
+        .args(["/T", "/PID", &pid.to_string()])
+        .creation_flags(0x08000000) // CREATE_NO_WINDOW
+        .output();
+    "taskkill"
+}
+
+#[cfg(unix)]
+fn send_to_group(pid: u32, signal: libc::c_int) {
+    // The child is the group leader, so its pid is the process group id
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), signal);
+    }
+}
+
+/// Whether any process in the group led by `pid` is still running.
+/// Zombies are ignored since they only wait to be reaped by their parent.
+#[cfg(target_os = "linux")]
+fn group_alive(pid: u32) -> bool {
+    let pgid = pid.to_string();
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return false;
+    };
+
+    entries.flatten().any(|entry| {
+        let Ok(stat) = std::fs::read_to_string(entry.path().join("stat")) else {
+            return false;
+        };
+        // Fields after the parenthesised command name: state, ppid, pgrp, ...
+        let Some((_, rest)) = stat.rsplit_once(')') else {
+            return false;
+        };
+        let mut fields = rest.split_whitespace();
+        let state = fields.next();
+        let pgrp = fields.nth(1);
+        pgrp == Some(pgid.as_str()) && state != Some("Z")
+    })
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn group_alive(pid: u32) -> bool {
+    unsafe { libc::kill(-(pid as libc::pid_t), 0) == 0 }
+}
+
+#[cfg(not(unix))]
+fn group_alive(_pid: u32) -> bool {
+    false
+}
+
+#[cfg(unix)]
+impl StopSignal {
+    fn as_raw(self) -> libc::c_int {
+        match self {
+            StopSignal::Term => libc::SIGTERM,
+            StopSignal::Int => libc::SIGINT,
+            StopSignal::Hup => libc::SIGHUP,
+            StopSignal::Quit => libc::SIGQUIT,
+            StopSignal::Kill => libc::SIGKILL,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            StopSignal::Term => "SIGTERM",
+            StopSignal::Int => "SIGINT",
+            StopSignal::Hup => "SIGHUP",
+            StopSignal::Quit => "SIGQUIT",
+            StopSignal::Kill => "SIGKILL",
+        }
+    }
+}
@@ -1,46 +1,498 @@
-use std::collections::HashMap;
-use std::io::{BufRead, BufReader, Write};
-use std::process::{Child, ChildStdin, Command, Stdio};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{self, PipeWriter, Write};
+use std::process::{Command, ExitStatus, Stdio};
 use std::sync::{Arc, Mutex};
-use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use serde::{Deserialize, Serialize};
-use tauri::{AppHandle, Emitter};
-
-/// Constants
+use tauri::AppHandle;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, ChildStderr, ChildStdout};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::config::{self, LimitAction, LogSettings, Probe, Project, PtyOptions, RestartMode, ShellProfile, StopSignal};
+use crate::dependency;
+use crate::env_file;
+use crate::events::{Event, EventBus};
+use crate::health::ProbeRunner;
+use crate::hooks::{HookEvent, HookRun};
+use crate::limits::{self, LimitKind};
+use crate::log_search::{LogSearch, Search};
+use crate::log_store::LogStore;
+use crate::process_group::{self, ExitDetails, StopOutcome};
+use crate::pty::{self, ControlKey, PtyMaster, PtyReader, Utf8Decoder};
+use crate::resources::{self, ResourceSample, TreeUsage};
+use crate::shell::ShellInvocation;
+
+// Constants
+/// Lines read from the log files for a unit that has not run in this session
 const MAX_LOG_LINES: usize = 1000;
-const MAX_RESTART_ATTEMPTS: u32 = 5;
-const RESTART_DELAY_MS: u64 = 2000;
+const DEPENDENCY_READY_TIMEOUT_SECS: u64 = 60;
+/// How long output is still collected after a process exits
+const OUTPUT_DRAIN_MS: u64 = 500;
+const STATS_INTERVAL_MS: u64 = 2000;
+/// Samples kept per project, five minutes at the default interval
+const STATS_HISTORY: usize = 150;
+/// Crashes kept per unit for the circuit breaker and `get_breaker_status`
+const CRASH_HISTORY: usize = 50;
+/// Output is logged and emitted in batches of at most this many lines and
+/// terminal chunks, or of what arrived within `LOG_BATCH_MS` of the first
+const LOG_BATCH_LINES: usize = 200;
+const LOG_BATCH_MS: u64 = 50;
+
+/// Output read from a process, on its way to the log
+enum Output {
+    Line(LogStream, String),
+    /// Raw terminal output, ANSI sequences included, for `process-output`;
+    /// its lines follow as `Line`s
+    Raw(String),
+}
 
 /// Process status enum
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum ProcessStatus {
     Stopped,
+    /// Running the setup steps before the main command
+    Setup,
+    /// Spawned, waiting for health probes to pass
+    Starting,
+    /// Spawned, no health probes configured
     Running,
+    /// Health probes are passing
+    Healthy,
+    /// The liveness probe is failing
+    Unhealthy,
     Error,
     Restarting,
+    /// Crash-looping; waiting out the circuit breaker's cool-off
+    Backoff,
+    /// Running the cleanup steps after the project stopped
+    Cleanup,
+}
+
+impl ProcessStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProcessStatus::Stopped => "stopped",
+            ProcessStatus::Setup => "setup",
+            ProcessStatus::Starting => "starting",
+            ProcessStatus::Running => "running",
+            ProcessStatus::Healthy => "healthy",
+            ProcessStatus::Unhealthy => "unhealthy",
+            ProcessStatus::Error => "error",
+            ProcessStatus::Restarting => "restarting",
+            ProcessStatus::Backoff => "backoff",
+            ProcessStatus::Cleanup => "cleanup",
+        }
+    }
+
+    /// Whether a child process, main command or step, is currently alive
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self,
+            ProcessStatus::Setup
+                | ProcessStatus::Starting
+                | ProcessStatus::Running
+                | ProcessStatus::Healthy
+                | ProcessStatus::Unhealthy
+                | ProcessStatus::Cleanup
+        )
+    }
+
+    /// Rank used to combine the statuses of a project's services
+    fn severity(&self) -> u8 {
+        match self {
+            ProcessStatus::Stopped => 0,
+            ProcessStatus::Healthy => 1,
+            ProcessStatus::Running => 2,
+            ProcessStatus::Cleanup => 3,
+            ProcessStatus::Setup => 4,
+            ProcessStatus::Starting => 5,
+            ProcessStatus::Restarting => 6,
+            ProcessStatus::Unhealthy => 7,
+            ProcessStatus::Backoff => 8,
+            ProcessStatus::Error => 9,
+        }
+    }
+
+    /// Status right after a spawn
+    fn initial(project: &Project) -> Self {
+        if project.health.is_empty() {
+            ProcessStatus::Running
+        } else {
+            ProcessStatus::Starting
+        }
+    }
+}
+
+/// The project, and service within it, an event is about
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnitRef {
+    pub project_id: String,
+    /// Set for events about one service of a project with services
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+}
+
+impl UnitRef {
+    pub fn new(unit_id: &str) -> Self {
+        let (project_id, service) = config::split_unit_id(unit_id);
+        Self {
+            project_id: project_id.to_string(),
+            service: service.map(str::to_string),
+        }
+    }
+}
+
+/// Where a log line came from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+    /// Input sent to the process, or a control key such as `^C`
+    Stdin,
+    /// Written by devboot itself: commands, exits, probes, hooks
+    System,
+}
+
+/// How serious a log line is; only DevBoot's own lines are ever errors
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    #[default]
+    Info,
+    /// A failure: a crash, a failed step, hook or probe, a spawn problem
+    Error,
+}
+
+/// One line of a unit's log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// Increases by one per line of the unit, across sessions
+    pub seq: u64,
+    /// RFC 3339
+    pub timestamp: String,
+    pub stream: LogStream,
+    /// Lines written before levels existed read as `info`
+    #[serde(default)]
+    pub level: LogLevel,
+    /// The run the line belongs to; increases on every spawn, including
+    /// setup and cleanup steps
+    pub run_id: u64,
+    pub text: String,
+}
+
+impl std::fmt::Display for LogEntry {
+    /// `[HH:MM:SS] text`, with input shown as `> text` and errors as
+    /// `error: text`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let time = chrono::DateTime::parse_from_rfc3339(&self.timestamp)
+            .map(|time| time.format("%H:%M:%S").to_string())
+            .unwrap_or_default();
+        match (self.stream, self.level) {
+            (LogStream::Stdin, _) => write!(f, "[{}] > {}", time, self.text),
+            (_, LogLevel::Error) => write!(f, "[{}] error: {}", time, self.text),
+            _ => write!(f, "[{}] {}", time, self.text),
+        }
+    }
+}
+
+/// Which way `get_log_page` reads from its cursor
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogDirection {
+    /// Towards older entries; without a cursor, the newest ones
+    #[default]
+    Before,
+    /// Towards newer entries; without a cursor, the oldest ones kept
+    After,
+}
+
+/// Part of a unit's log in memory
+#[derive(Debug, Clone, Serialize)]
+pub struct LogPage {
+    /// Oldest first
+    pub entries: Vec<LogEntry>,
+    /// Whether more entries are kept past this page in the direction read
+    pub has_more: bool,
+    /// Oldest entry still kept in memory
+    pub first_seq: Option<u64>,
+    /// Sequence number the next line will get
+    pub next_seq: u64,
+}
+
+impl LogPage {
+    /// Up to `limit` entries of `logs` (sorted by `seq`) before or after
+    /// `cursor`, which itself is never included
+    fn read(
+        logs: &VecDeque<LogEntry>,
+        next_seq: u64,
+        cursor: Option<u64>,
+        direction: LogDirection,
+        limit: usize,
+    ) -> Self {
+        let (start, end, has_more) = match direction {
+            LogDirection::Before => {
+                let end = cursor.map_or(logs.len(), |cursor| logs.partition_point(|entry| entry.seq < cursor));
+                let start = end.saturating_sub(limit);
+                (start, end, start > 0)
+            }
+            LogDirection::After => {
+                let start = cursor.map_or(0, |cursor| logs.partition_point(|entry| entry.seq <= cursor));
+                let end = start.saturating_add(limit).min(logs.len());
+                (start, end, end < logs.len())
+            }
+        };
+        Self {
+            entries: logs.range(start..end).cloned().collect(),
+            has_more,
+            first_seq: logs.front().map(|entry| entry.seq),
+            next_seq,
+        }
+    }
 }
 
 /// Event payloads for frontend
 #[derive(Clone, Serialize)]
 pub struct LogPayload {
-    pub project_id: String,
-    pub log: String,
+    #[serde(flatten)]
+    pub unit: UnitRef,
+    /// Oldest first
+    pub logs: Vec<LogEntry>,
+}
+
+/// Raw terminal output from a project running in a pseudo-terminal
+#[derive(Clone, Serialize)]
+pub struct OutputPayload {
+    #[serde(flatten)]
+    pub unit: UnitRef,
+    pub data: String,
 }
 
+/// Emitted for the unit that changed and, for a service, again without
+/// `service` for the combined status of its project
 #[derive(Clone, Serialize)]
 pub struct StatusPayload {
-    pub project_id: String,
+    #[serde(flatten)]
+    pub unit: UnitRef,
     pub status: String,
+    /// The setup or cleanup step that is running, or that failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub step: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct StatsPayload {
+    #[serde(flatten)]
+    pub unit: UnitRef,
+    #[serde(flatten)]
+    pub sample: ResourceSample,
+}
+
+/// A limit enforced by the watchdog
+#[derive(Clone, Serialize)]
+pub struct LimitPayload {
+    #[serde(flatten)]
+    pub unit: UnitRef,
+    pub limit: LimitKind,
+    pub action: LimitAction,
+    pub message: String,
 }
 
 #[derive(Clone, Serialize)]
 pub struct CrashPayload {
-    pub project_id: String,
+    #[serde(flatten)]
+    pub unit: UnitRef,
     pub restart_count: u32,
+    /// Missing when the process was killed by a signal
+    pub exit_code: Option<i32>,
+    /// Name of the signal that killed the process, e.g. `SIGSEGV` (Unix only)
+    pub signal: Option<String>,
+    pub core_dumped: bool,
     pub will_restart: bool,
+    /// Delay before the next attempt, when one is scheduled
+    pub retry_delay_ms: Option<u64>,
+    /// RFC 3339 time of the next attempt, when one is scheduled
+    pub next_retry_at: Option<String>,
+}
+
+/// One crash of a unit, kept in `ProcessInfo::crashes`
+#[derive(Debug, Clone, Serialize)]
+pub struct CrashRecord {
+    /// RFC 3339 time of the crash
+    pub at: String,
+    pub exit_code: Option<i32>,
+    pub signal: Option<String>,
+    pub core_dumped: bool,
+    #[serde(skip)]
+    instant: Instant,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Crashes are restarted as the restart policy says
+    #[default]
+    Closed,
+    /// Tripped; the unit is in `Backoff` until `next_attempt_at`
+    Open,
+}
+
+/// Circuit breaker of a unit, returned by `get_breaker_status` and emitted
+/// as `process-breaker` when it opens or closes
+#[derive(Clone, Serialize)]
+pub struct BreakerPayload {
+    #[serde(flatten)]
+    pub unit: UnitRef,
+    pub state: BreakerState,
+    /// How often the breaker has tripped since DevBoot started
+    pub trips: u32,
+    /// RFC 3339 time of the next attempt while open
+    pub next_attempt_at: Option<String>,
+    /// Recent crashes, oldest first
+    pub crashes: Vec<CrashRecord>,
+}
+
+/// Where keystrokes for a running project are written.
+/// Cloned out of the handle table so a write never holds the table's lock.
+#[derive(Debug, Clone)]
+pub enum ProcessInput {
+    /// Writes block while the pipe is full, so they go one at a time
+    Pipe(Arc<Mutex<PipeWriter>>),
+    /// Writes fail instead of blocking while the terminal's buffer is full
+    Pty(Arc<PtyMaster>),
+}
+
+impl ProcessInput {
+    /// Write and flush `bytes`
+    fn send(&self, bytes: &[u8]) -> io::Result<()> {
+        match self {
+            ProcessInput::Pipe(stdin) => {
+                let mut stdin = stdin.lock().unwrap();
+                stdin.write_all(bytes)?;
+                stdin.flush()
+            }
+            ProcessInput::Pty(master) => {
+                let mut master = &**master;
+                master.write_all(bytes)?;
+                master.flush()
+            }
+        }
+    }
+}
+
+/// Output streams of a freshly spawned process
+enum ProcessOutput {
+    Pipes(Option<ChildStdout>, Option<ChildStderr>),
+    /// Master side of the PTY; stdout and stderr are merged by the terminal
+    Pty(PtyReader),
+}
+
+/// A freshly spawned process and its stdio
+struct Spawned {
+    child: Child,
+    pid: u32,
+    input: Option<ProcessInput>,
+    output: ProcessOutput,
+    command_line: String,
+    /// Problems that did not prevent the spawn, such as a PTY fallback
+    warnings: Vec<String>,
+}
+
+/// Requests handled by the supervisor of a run
+#[derive(Debug)]
+enum RunControl {
+    /// Stop the process tree gracefully and report how it ended
+    Stop {
+        signal: StopSignal,
+        grace: Duration,
+        reply: oneshot::Sender<StopOutcome>,
+    },
+    /// Kill the process tree right away; the exit is handled like a crash
+    Kill,
+}
+
+/// How waiting on a process ended
+enum Exit {
+    Exited(io::Result<ExitStatus>),
+    /// Stopped on request; `reply` is answered once any cleanup is done
+    Stopped {
+        reply: oneshot::Sender<StopOutcome>,
+        outcome: StopOutcome,
+    },
+}
+
+/// What the supervisor does after the main process exited.
+/// `hooks` run first, in order.
+enum AfterExit {
+    /// Restart after `delay`, unless the unit leaves `status` meanwhile
+    Restart {
+        project: Project,
+        status: ProcessStatus,
+        restart_count: u32,
+        delay: Duration,
+        hooks: Vec<HookRun>,
+    },
+    /// No restart; run the cleanup steps and leave the unit in `status`
+    Done {
+        project: Project,
+        status: ProcessStatus,
+        hooks: Vec<HookRun>,
+    },
+    /// Stopped by the user or replaced by a newer run
+    Ignore,
+}
+
+/// Lists of steps run around the main command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    Setup,
+    Cleanup,
+}
+
+impl Stage {
+    fn name(self) -> &'static str {
+        match self {
+            Stage::Setup => "setup",
+            Stage::Cleanup => "cleanup",
+        }
+    }
+
+    fn status(self) -> ProcessStatus {
+        match self {
+            Stage::Setup => ProcessStatus::Setup,
+            Stage::Cleanup => ProcessStatus::Cleanup,
+        }
+    }
+}
+
+/// A stage begun by `begin_stage`, to be run by `run_stage`
+struct StageRun {
+    stage: Stage,
+    run_id: u64,
+    control: mpsc::UnboundedReceiver<RunControl>,
+}
+
+enum StageResult {
+    /// Every step ran; holds the stage's run id
+    Done(u64),
+    /// A setup step failed, or the unit was replaced meanwhile
+    Failed,
+    Stopped {
+        reply: oneshot::Sender<StopOutcome>,
+        outcome: StopOutcome,
+    },
+}
+
+/// The parts of a run owned by its supervisor task
+struct Run {
+    run_id: u64,
+    pid: u32,
+    child: Child,
+    output: ProcessOutput,
+    control: mpsc::UnboundedReceiver<RunControl>,
 }
 
 /// Process info for a running project
@@ -48,565 +500,1982 @@ pub struct CrashPayload {
 pub struct ProcessInfo {
     #[allow(dead_code)]
     pub project_id: String,
-    pub child: Option<Child>,
+    /// Process group leader of the current run
+    pub pid: Option<u32>,
+    /// Channel to the supervisor of the current run
+    control: Option<mpsc::UnboundedSender<RunControl>>,
     pub status: ProcessStatus,
-    pub logs: Vec<String>,
+    /// The last `project.log_lines` entries, oldest first
+    pub logs: VecDeque<LogEntry>,
     pub restart_count: u32,
-    pub restart_on_crash: bool,
-    pub path: String,
-    pub commands: Vec<String>,
+    pub project: Project,
+    /// Incremented on every spawn so per-run threads can tell they are stale
+    pub run_id: u64,
+    /// Sequence number of the next line, used as a cursor by log pattern probes
+    pub next_seq: u64,
+    /// Set when the liveness probe killed the process, so the exit counts as a crash
+    pub liveness_failed: bool,
+    /// When the current run was spawned
+    pub started_at: Option<Instant>,
+    /// Last size requested through `resize_terminal`, reused on restart
+    pub terminal_size: Option<PtyOptions>,
+    /// Recent resource samples, oldest first, kept across restarts
+    pub stats: VecDeque<ResourceSample>,
+    /// Limits already enforced during the current run
+    pub limits_hit: HashSet<LimitKind>,
+    /// Setup or cleanup step that is running, or that failed, e.g. `setup 1/2: npm ci`
+    pub step: Option<String>,
+    /// Recent crashes, oldest first, kept across restarts
+    pub crashes: VecDeque<CrashRecord>,
+    pub breaker: BreakerState,
+    pub breaker_trips: u32,
+    /// RFC 3339 time the cool-off ends while the breaker is open
+    pub next_attempt_at: Option<String>,
+    /// When the breaker last closed; earlier crashes no longer count towards tripping it
+    pub breaker_closed_at: Option<Instant>,
+    /// Start of the second output lines are counted in for `max_log_rate`
+    log_window: Option<Instant>,
+    /// Output lines logged in that second
+    log_window_lines: u32,
+    /// Output lines dropped since they were last reported
+    suppressed: u64,
+    /// Also receives every line added to `logs`
+    log_store: LogStore,
 }
 
 impl ProcessInfo {
-    pub fn new(project_id: String, path: String, commands: Vec<String>, restart_on_crash: bool) -> Self {
+    /// Starts with the tail of the unit's log on disk, e.g. from the last
+    /// session, and carries on its sequence numbers and run ids
+    pub fn new(project: Project, log_store: LogStore) -> Self {
+        let logs: VecDeque<_> = log_store.tail(&project.id, project.log_lines).into();
+        let (next_seq, run_id) = logs
+            .back()
+            .map(|entry| (entry.seq + 1, entry.run_id))
+            .unwrap_or((0, 0));
         Self {
-            project_id,
-            child: None,
+            project_id: project.id.clone(),
+            pid: None,
+            control: None,
             status: ProcessStatus::Stopped,
-            logs: Vec::new(),
+            logs,
             restart_count: 0,
-            restart_on_crash,
-            path,
-            commands,
+            project,
+            run_id,
+            next_seq,
+            liveness_failed: false,
+            started_at: None,
+            terminal_size: None,
+            stats: VecDeque::new(),
+            limits_hit: HashSet::new(),
+            step: None,
+            crashes: VecDeque::new(),
+            breaker: BreakerState::Closed,
+            breaker_trips: 0,
+            next_attempt_at: None,
+            breaker_closed_at: None,
+            log_window: None,
+            log_window_lines: 0,
+            suppressed: 0,
+            log_store,
+        }
+    }
+
+    /// Log a line of the current run, returning the entry to emit
+    pub fn add_log(&mut self, stream: LogStream, text: String) -> LogEntry {
+        self.add_entry(stream, LogLevel::Info, text)
+    }
+
+    /// Log a failure reported by DevBoot itself
+    pub fn add_error(&mut self, text: String) -> LogEntry {
+        self.add_entry(LogStream::System, LogLevel::Error, text)
+    }
+
+    fn add_entry(&mut self, stream: LogStream, level: LogLevel, text: String) -> LogEntry {
+        let entry = LogEntry {
+            seq: self.next_seq,
+            timestamp: chrono::Local::now().to_rfc3339(),
+            stream,
+            level,
+            run_id: self.run_id,
+            text,
+        };
+        // Keep only the last `log_lines` lines
+        while self.logs.len() >= self.project.log_lines.max(1) {
+            self.logs.pop_front();
+        }
+        self.log_store.append(&self.project_id, &entry);
+        self.logs.push_back(entry.clone());
+        self.next_seq += 1;
+        entry
+    }
+
+    /// Log a batch of output, dropping the lines past the project's
+    /// `max_log_rate` in each second, and the raw terminal output read
+    /// while over it. Returns the entries and raw output to emit.
+    fn add_output(&mut self, batch: Vec<Output>) -> (Vec<LogEntry>, String) {
+        let max_rate = self.project.max_log_rate;
+        let mut entries = Vec::with_capacity(batch.len() + 1);
+        let mut raw = String::new();
+        entries.extend(self.roll_log_window());
+        for output in batch {
+            entries.extend(self.roll_log_window());
+            let limited = max_rate > 0 && self.log_window_lines >= max_rate;
+            match output {
+                Output::Raw(data) if !limited => raw.push_str(&data),
+                Output::Raw(_) => {}
+                Output::Line(..) if limited => self.suppressed += 1,
+                Output::Line(stream, text) => {
+                    self.log_window_lines += 1;
+                    entries.push(self.add_log(stream, text));
+                }
+            }
+        }
+        (entries, raw)
+    }
+
+    /// Start counting a new second once the current one is over,
+    /// reporting the lines dropped in it
+    fn roll_log_window(&mut self) -> Option<LogEntry> {
+        let now = Instant::now();
+        if self.log_window.is_some_and(|start| now.duration_since(start) < Duration::from_secs(1)) {
+            return None;
+        }
+        self.log_window = Some(now);
+        self.log_window_lines = 0;
+        self.report_suppressed()
+    }
+
+    /// Log how many lines were dropped, if any
+    fn report_suppressed(&mut self) -> Option<LogEntry> {
+        if self.suppressed == 0 {
+            return None;
         }
+        let message = format!(
+            "{} lines suppressed, output exceeded {} lines/s",
+            std::mem::take(&mut self.suppressed),
+            self.project.max_log_rate
+        );
+        Some(self.add_error(message))
+    }
+
+    /// When the lines dropped so far are due to be reported
+    fn suppressed_until(&self) -> Option<Instant> {
+        if self.suppressed == 0 {
+            return None;
+        }
+        self.log_window.map(|start| start + Duration::from_secs(1))
+    }
+
+    fn record_crash(&mut self, exit: &ExitDetails) {
+        if self.crashes.len() >= CRASH_HISTORY {
+            self.crashes.pop_front();
+        }
+        self.crashes.push_back(CrashRecord {
+            at: chrono::Local::now().to_rfc3339(),
+            exit_code: exit.code,
+            signal: exit.signal_name(),
+            core_dumped: exit.core_dumped,
+            instant: Instant::now(),
+        });
+    }
+
+    /// Crashes within `window` that count towards tripping the breaker
+    fn recent_crashes(&self, window: Duration) -> usize {
+        self.crashes
+            .iter()
+            .filter(|crash| crash.instant.elapsed() <= window)
+            .filter(|crash| self.breaker_closed_at.is_none_or(|closed| crash.instant >= closed))
+            .count()
     }
 
-    pub fn add_log(&mut self, line: String) {
-        // Keep only last MAX_LOG_LINES lines
-        if self.logs.len() >= MAX_LOG_LINES {
-            self.logs.remove(0);
+    fn breaker_payload(&self, unit_id: &str) -> BreakerPayload {
+        BreakerPayload {
+            unit: UnitRef::new(unit_id),
+            state: self.breaker,
+            trips: self.breaker_trips,
+            next_attempt_at: self.next_attempt_at.clone(),
+            crashes: self.crashes.iter().cloned().collect(),
         }
-        self.logs.push(line);
+    }
+
+    /// Close the breaker, so only crashes from now on can trip it again
+    fn close_breaker(&mut self) {
+        self.breaker = BreakerState::Closed;
+        self.next_attempt_at = None;
+        self.breaker_closed_at = Some(Instant::now());
     }
 }
 
-/// Process manager to handle all running processes
+/// Process manager to handle all running processes.
+/// Cloning is cheap and yields a handle to the same shared state.
+///
+/// Processes are tracked per unit (see `config::unit_id`): a project without
+/// services is one unit, a project with services has one per service.
+/// Lifecycle and status methods accept a project id, covering all of its
+/// units, or the unit id of a single service.
+#[derive(Clone)]
 pub struct ProcessManager {
     processes: Arc<Mutex<HashMap<String, ProcessInfo>>>,
-    stdin_handles: Arc<Mutex<HashMap<String, ChildStdin>>>,
-    git_bash_path: String,
-    app_handle: Arc<Mutex<Option<AppHandle>>>,
+    stdin_handles: Arc<Mutex<HashMap<String, ProcessInput>>>,
+    default_shell: Arc<Mutex<ShellProfile>>,
+    events: Arc<EventBus>,
+    log_store: LogStore,
 }
 
 impl ProcessManager {
     pub fn new() -> Self {
-        let git_bash_path = Self::find_git_bash();
-        
         Self {
             processes: Arc::new(Mutex::new(HashMap::new())),
             stdin_handles: Arc::new(Mutex::new(HashMap::new())),
-            git_bash_path,
-            app_handle: Arc::new(Mutex::new(None)),
+            default_shell: Arc::new(Mutex::new(ShellProfile::default())),
+            events: Arc::new(EventBus::new()),
+            log_store: LogStore::new(),
         }
     }
 
+    /// Set the shell used by projects without their own shell profile
+    pub fn set_default_shell(&self, shell: ShellProfile) {
+        *self.default_shell.lock().unwrap() = shell;
+    }
+
+    /// Apply new settings for the logs on disk
+    pub fn set_log_settings(&self, settings: LogSettings) {
+        self.log_store.configure(settings);
+    }
+
     /// Set app handle for emitting events
     pub fn set_app_handle(&self, handle: AppHandle) {
-        let mut app_handle = self.app_handle.lock().unwrap();
-        *app_handle = Some(handle);
+        self.events.set_app_handle(handle);
     }
 
-    /// Emit event to frontend
-    fn emit_event<S: Serialize + Clone>(&self, event: &str, payload: S) {
-        if let Some(handle) = self.app_handle.lock().unwrap().as_ref() {
-            let _ = handle.emit(event, payload);
-        }
+    /// Receive every event emitted from now on, for clients other than the window
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<Event> {
+        self.events.subscribe()
     }
 
-    /// Find Git Bash executable path
-    fn find_git_bash() -> String {
-        let possible_paths = vec![
-            "C:\\Program Files\\Git\\bin\\bash.exe",
-            "C:\\Program Files (x86)\\Git\\bin\\bash.exe",
-            "C:\\Git\\bin\\bash.exe",
-        ];
+    /// Sample the CPU and memory usage of every running project's process
+    /// tree at a fixed interval, keeping a short history and emitting a
+    /// `process-stats` event per sample. Only Linux reports usage.
+    ///
+    /// Each tick also runs the watchdog that enforces the memory and
+    /// runtime limits in `Project::limits`.
+    pub fn start_resource_sampler(&self) {
+        let manager = self.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_millis(STATS_INTERVAL_MS));
+            // Previous CPU ticks per project, keyed by the pid they were read from
+            let mut previous: HashMap<String, (u32, u64, Instant)> = HashMap::new();
+            loop {
+                interval.tick().await;
+                manager.sample_resources(&mut previous);
+            }
+        });
+    }
 
-        for path in possible_paths {
-            if std::path::Path::new(path).exists() {
-                return path.to_string();
+    fn sample_resources(&self, previous: &mut HashMap<String, (u32, u64, Instant)>) {
+        let running: Vec<(String, u32)> = {
+            let procs = self.processes.lock().unwrap();
+            procs
+                .iter()
+                .filter_map(|(id, info)| Some((id.clone(), info.pid?)))
+                .collect()
+        };
+        previous.retain(|id, _| running.iter().any(|(running_id, _)| running_id == id));
+
+        let ticks_per_second = resources::ticks_per_second() as f64;
+        for (project_id, pid) in running {
+            let usage = resources::sample_tree(pid);
+            self.enforce_limits(&project_id, usage.as_ref());
+            let Some(usage) = usage else {
+                continue;
+            };
+
+            let now = Instant::now();
+            // The first sample of a run has nothing to compare against
+            let cpu_percent = match previous.get(&project_id) {
+                Some(&(prev_pid, prev_ticks, prev_at)) if prev_pid == pid => {
+                    let elapsed = now.duration_since(prev_at).as_secs_f64();
+                    // Ticks of children that exited since are lost, so never go negative
+                    let ticks = usage.cpu_ticks.saturating_sub(prev_ticks) as f64;
+                    if elapsed > 0.0 {
+                        ticks / ticks_per_second / elapsed * 100.0
+                    } else {
+                        0.0
+                    }
+                }
+                _ => 0.0,
+            };
+            previous.insert(project_id.clone(), (pid, usage.cpu_ticks, now));
+
+            let sample = ResourceSample {
+                timestamp: chrono::Local::now().to_rfc3339(),
+                cpu_percent,
+                rss_bytes: usage.rss_bytes,
+                threads: usage.threads,
+                children: usage.processes.saturating_sub(1),
+            };
+            {
+                let mut procs = self.processes.lock().unwrap();
+                let Some(info) = procs.get_mut(&project_id) else {
+                    continue;
+                };
+                if info.stats.len() >= STATS_HISTORY {
+                    info.stats.pop_front();
+                }
+                info.stats.push_back(sample.clone());
             }
+            self.emit_event("process-stats", StatsPayload {
+                unit: UnitRef::new(&project_id),
+                sample,
+            });
         }
-
-        // Fallback - try to find in PATH
-        "bash".to_string()
     }
 
-    /// Start a project process
-    pub fn start_project(
-        &self,
-        project_id: &str,
-        path: &str,
-        commands: &[String],
-        restart_on_crash: bool,
-    ) -> Result<(), String> {
-        // Check if already running
-        {
-            let procs = self.processes.lock().unwrap();
-            if let Some(info) = procs.get(project_id) {
-                if info.status == ProcessStatus::Running {
-                    return Err("Project is already running".to_string());
+    /// Apply the limit action for each limit newly exceeded by the current
+    /// run, logging it and emitting a `process-limit` event
+    fn enforce_limits(&self, project_id: &str, usage: Option<&TreeUsage>) {
+        let (violations, action) = {
+            let mut procs = self.processes.lock().unwrap();
+            let Some(info) = procs.get_mut(project_id) else {
+                return;
+            };
+            // Limits apply to the main command; `started_at` and `limits_hit`
+            // still describe the previous run while steps run
+            if matches!(info.status, ProcessStatus::Setup | ProcessStatus::Cleanup) {
+                return;
+            }
+            let uptime = info.started_at.map(|t| t.elapsed()).unwrap_or_default();
+            let limits = &info.project.limits;
+            let violations: Vec<_> = limits::check(limits, usage, uptime)
+                .into_iter()
+                .filter(|violation| !info.limits_hit.contains(&violation.kind))
+                .collect();
+            info.limits_hit.extend(violations.iter().map(|violation| violation.kind));
+            (violations, limits.action)
+        };
+        if violations.is_empty() {
+            return;
+        }
+
+        for violation in &violations {
+            let verb = match action {
+                LimitAction::Log => "",
+                LimitAction::Restart => ", restarting",
+                LimitAction::Stop => ", stopping",
+            };
+            Self::push_error(
+                &self.processes,
+                &self.events,
+                project_id,
+                format!("Watchdog: {}{}", violation.message, verb),
+            );
+            self.emit_event("process-limit", LimitPayload {
+                unit: UnitRef::new(project_id),
+                limit: violation.kind,
+                action,
+                message: violation.message.clone(),
+            });
+        }
+
+        if action == LimitAction::Log {
+            return;
+        }
+        let manager = self.clone();
+        let project_id = project_id.to_string();
+        tauri::async_runtime::spawn(async move {
+            let project = manager
+                .processes
+                .lock()
+                .unwrap()
+                .get(&project_id)
+                .map(|info| info.project.clone());
+            let _ = manager.stop(&project_id).await;
+            if let (LimitAction::Restart, Some(project)) = (action, project) {
+                if let Err(e) = manager.start_project(&project) {
+                    Self::push_error(&manager.processes, &manager.events, &project_id, format!("Failed to restart: {}", e));
                 }
             }
+        });
+    }
+
+    /// Emit event to frontend
+    fn emit_event<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        self.events.emit(event, payload);
+    }
+
+    /// Build the command for a project using its shell profile.
+    /// Returns the command and its printable command line.
+    fn build_command(project: &Project, default_shell: ShellProfile) -> Result<(Command, String), String> {
+        if !std::path::Path::new(&project.path).is_dir() {
+            return Err(format!("Project path does not exist: {}", project.path));
         }
 
-        self.spawn_process(project_id, path, commands, restart_on_crash, 0)
+        let invocation = ShellInvocation::new(project.shell.unwrap_or(default_shell), &project.commands)?;
+        let mut command = invocation.to_command();
+        command.current_dir(&project.path);
+        Ok((command, invocation.display()))
     }
 
-    /// Internal spawn process (used for initial start and restarts)
-    fn spawn_process(
-        &self,
-        project_id: &str,
-        path: &str,
-        commands: &[String],
-        restart_on_crash: bool,
-        restart_count: u32,
-    ) -> Result<(), String> {
-        // Build the full command
-        let cd_command = format!("cd '{}'", path.replace('\\', "/"));
-        let full_commands: Vec<String> = std::iter::once(cd_command)
-            .chain(commands.iter().cloned())
-            .collect();
-        let script = full_commands.join(" && ");
+    /// Spawn a project's command with its environment, attached to a
+    /// pseudo-terminal of `terminal_size` when the project uses PTY mode and
+    /// to pipes otherwise. PTY mode falls back to pipes where it is not
+    /// supported, which is reported in `Spawned::warnings`.
+    ///
+    /// Stdin is a plain pipe so input can be written without the runtime;
+    /// stdout and stderr are read asynchronously by the run's supervisor.
+    fn spawn_child(
+        project: &Project,
+        default_shell: ShellProfile,
+        env: &HashMap<String, String>,
+        terminal_size: Option<PtyOptions>,
+    ) -> Result<Spawned, String> {
+        let (mut command, command_line) = Self::build_command(project, default_shell)?;
+        limits::apply(&mut command, &project.limits);
+        let mut warnings = Vec::new();
+
+        // Child reaping and the PTY reader are driven by the tokio runtime
+        let runtime = tauri::async_runtime::handle();
+        let _runtime_guard = runtime.inner().enter();
+
+        let mut pty_master = None;
+        if let Some(size) = project.pty.map(|options| terminal_size.unwrap_or(options)) {
+            match pty::open(size.cols, size.rows) {
+                Ok((master, slave)) => {
+                    pty::attach(&mut command, &slave)?;
+                    let reader = master
+                        .reader()
+                        .map_err(|e| format!("Failed to read pseudo-terminal: {}", e))?;
+                    command.env("TERM", "xterm-256color");
+                    pty_master = Some((master, reader));
+                }
+                Err(e) => warnings.push(format!("{}, using pipes instead", e)),
+            }
+        }
+
+        let mut stdin = None;
+        if pty_master.is_none() {
+            // Own process group so stopping reaches grandchildren too
+            process_group::set_new_group(&mut command);
+            let (reader, writer) =
+                io::pipe().map_err(|e| format!("Failed to create stdin pipe: {}", e))?;
+            command
+                .stdin(reader)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped());
+            stdin = Some(writer);
+        }
 
         // Spawn the process with UTF-8 encoding for Python and other tools
-        let mut child = Command::new(&self.git_bash_path)
-            .args(["-c", &script])
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
+        command
             // Set UTF-8 encoding environment variables
             .env("PYTHONIOENCODING", "utf-8")
             .env("PYTHONUTF8", "1")
             .env("LANG", "en_US.UTF-8")
             .env("LC_ALL", "en_US.UTF-8")
-            .creation_flags(0x08000000) // CREATE_NO_WINDOW on Windows
+            // Project env files and env vars override the defaults above
+            .envs(env)
+            .creation_flags(0x08000000); // CREATE_NO_WINDOW on Windows
+        // Converting consumes the command, which releases our copies of the
+        // PTY slave and the stdin read end once the child has them
+        let mut child = tokio::process::Command::from(command)
             .spawn()
             .map_err(|e| format!("Failed to start process: {}", e))?;
+        let pid = child.id().unwrap_or_default();
+
+        let (input, output) = match pty_master {
+            Some((master, reader)) => (Some(ProcessInput::Pty(Arc::new(master))), ProcessOutput::Pty(reader)),
+            None => (
+                stdin.map(|stdin| ProcessInput::Pipe(Arc::new(Mutex::new(stdin)))),
+                ProcessOutput::Pipes(child.stdout.take(), child.stderr.take()),
+            ),
+        };
+
+        Ok(Spawned {
+            child,
+            pid,
+            input,
+            output,
+            command_line,
+            warnings,
+        })
+    }
 
-        // Capture stdin, stdout, and stderr
-        let stdin = child.stdin.take();
-        let stdout = child.stdout.take();
-        let stderr = child.stderr.take();
-        let pid = project_id.to_string();
+    /// Start a project, or those of its services that are not running
+    pub fn start_project(&self, project: &Project) -> Result<(), String> {
+        let units: Vec<Project> = project
+            .units()
+            .into_iter()
+            .filter(|unit| !self.unit_is_active(&unit.id))
+            .collect();
+        if units.is_empty() {
+            return Err("Project is already running".to_string());
+        }
+        if project.services.is_empty() {
+            return self.spawn_process(project, 0, true);
+        }
 
-        // Store stdin handle separately (ChildStdin is not Send/Sync safe in ProcessInfo)
-        if let Some(stdin_handle) = stdin {
-            let mut stdin_handles = self.stdin_handles.lock().unwrap();
-            stdin_handles.insert(project_id.to_string(), stdin_handle);
+        // One service failing to spawn does not keep the others down
+        let mut errors = Vec::new();
+        for unit in &units {
+            if let Err(e) = self.spawn_process(unit, 0, true) {
+                self.add_system_error(unit, format!("Failed to start: {}", e));
+                let (_, service) = config::split_unit_id(&unit.id);
+                errors.push(format!("{}: {}", service.unwrap_or_default(), e));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Failed to start services: {}", errors.join("; ")))
         }
+    }
 
-        // Create or update process info
-        {
-            let mut procs = self.processes.lock().unwrap();
-            let info = procs.entry(project_id.to_string()).or_insert_with(|| {
-                ProcessInfo::new(
-                    project_id.to_string(),
-                    path.to_string(),
-                    commands.to_vec(),
-                    restart_on_crash,
-                )
-            });
-            info.status = ProcessStatus::Running;
-            info.child = Some(child);
-            info.restart_count = restart_count;
-            info.restart_on_crash = restart_on_crash;
-            info.path = path.to_string();
-            info.commands = commands.to_vec();
+    /// Start one service of a project
+    pub fn start_service(&self, project: &Project, service: &str) -> Result<(), String> {
+        let unit = project
+            .find_service_unit(service)
+            .ok_or_else(|| format!("Service not found: {}", service))?;
+        if self.unit_is_active(&unit.id) {
+            return Err("Service is already running".to_string());
         }
+        self.spawn_process(&unit, 0, true)
+    }
 
-        // Emit status changed event
-        self.emit_event("process-status", StatusPayload {
-            project_id: pid.clone(),
-            status: "running".to_string(),
-        });
+    fn unit_is_active(&self, unit_id: &str) -> bool {
+        let procs = self.processes.lock().unwrap();
+        procs.get(unit_id).is_some_and(|info| info.status.is_active())
+    }
 
-        let processes = Arc::clone(&self.processes);
-        let app_handle = Arc::clone(&self.app_handle);
-
-        // Spawn thread to read stdout
-        if let Some(stdout) = stdout {
-            let processes = Arc::clone(&processes);
-            let app_handle = Arc::clone(&app_handle);
-            let pid = pid.clone();
-            
-            thread::spawn(move || {
-                let reader = BufReader::new(stdout);
-                for line in reader.lines() {
-                    if let Ok(line) = line {
-                        let timestamp = chrono::Local::now().format("%H:%M:%S").to_string();
-                        let log_line = format!("[{}] {}", timestamp, line);
-                        
-                        // Add to logs
-                        {
-                            let mut procs = processes.lock().unwrap();
-                            if let Some(info) = procs.get_mut(&pid) {
-                                info.add_log(log_line.clone());
-                            }
-                        }
+    /// Process table keys covered by `id`: a project's own entry and those of
+    /// its services, or the entry of a single unit
+    fn unit_keys(procs: &HashMap<String, ProcessInfo>, id: &str) -> Vec<String> {
+        procs
+            .keys()
+            .filter(|key| key.as_str() == id || config::split_unit_id(key).0 == id)
+            .cloned()
+            .collect()
+    }
 
-                        // Emit log event
-                        if let Some(handle) = app_handle.lock().unwrap().as_ref() {
-                            let _ = handle.emit("process-log", LogPayload {
-                                project_id: pid.clone(),
-                                log: log_line,
-                            });
-                        }
+    /// The most pressing status among the units covered by `id`
+    fn combined_status(procs: &HashMap<String, ProcessInfo>, id: &str) -> ProcessStatus {
+        Self::unit_keys(procs, id)
+            .iter()
+            .filter_map(|key| procs.get(key))
+            .map(|info| info.status.clone())
+            .max_by_key(ProcessStatus::severity)
+            .unwrap_or(ProcessStatus::Stopped)
+    }
+
+    /// Start projects in dependency order on a task of their own.
+    ///
+    /// `plan` must already be ordered (see `dependency::start_order`). Projects
+    /// that are already running are skipped, and each project another one in
+    /// the plan depends on must become ready before the plan continues. If a
+    /// dependency fails, the projects that need it are not started and the
+    /// reason is written to their logs.
+    pub fn start_in_order(&self, plan: Vec<Project>) {
+        let manager = self.clone();
+        tauri::async_runtime::spawn(async move {
+            let required = dependency::required_by_others(&plan);
+            let mut failed: HashSet<String> = HashSet::new();
+
+            for project in &plan {
+                let blocked_by = project
+                    .depends_on
+                    .iter()
+                    .filter_map(|dep| dependency::find_project(&plan, dep))
+                    .find(|dep| failed.contains(&dep.id));
+                if let Some(dep) = blocked_by {
+                    manager.add_system_error(
+                        project,
+                        format!("Not started: dependency '{}' is not ready", dep.name),
+                    );
+                    failed.insert(project.id.clone());
+                    continue;
+                }
+
+                if !manager.is_running(&project.id) {
+                    if let Err(e) = manager.start_project(project) {
+                        manager.add_system_error(project, format!("Failed to start: {}", e));
+                        failed.insert(project.id.clone());
+                        continue;
                     }
                 }
-            });
-        }
 
-        // Spawn thread to read stderr (many tools output to stderr, not just errors)
-        if let Some(stderr) = stderr {
-            let processes = Arc::clone(&processes);
-            let app_handle = Arc::clone(&app_handle);
-            let pid = pid.clone();
-            
-            thread::spawn(move || {
-                let reader = BufReader::new(stderr);
-                for line in reader.lines() {
-                    if let Ok(line) = line {
-                        let timestamp = chrono::Local::now().format("%H:%M:%S").to_string();
-                        // Don't prefix with [ERR] - many tools use stderr for normal output
-                        let log_line = format!("[{}] {}", timestamp, line);
-                        
-                        {
-                            let mut procs = processes.lock().unwrap();
-                            if let Some(info) = procs.get_mut(&pid) {
-                                info.add_log(log_line.clone());
-                            }
-                        }
+                if required.contains(&project.id) {
+                    let timeout = Duration::from_secs(DEPENDENCY_READY_TIMEOUT_SECS);
+                    if let Err(e) = manager.wait_until_ready(&project.id, timeout).await {
+                        manager.add_system_error(project, e);
+                        failed.insert(project.id.clone());
+                    }
+                }
+            }
+        });
+    }
 
-                        if let Some(handle) = app_handle.lock().unwrap().as_ref() {
-                            let _ = handle.emit("process-log", LogPayload {
-                                project_id: pid.clone(),
-                                log: log_line,
-                            });
-                        }
+    /// Wait until a project is ready to serve its dependents, checking its
+    /// status each time a unit's status changes
+    async fn wait_until_ready(&self, project_id: &str, timeout: Duration) -> Result<(), String> {
+        let mut changes = self.events.watch_status();
+        let ready = tokio::time::timeout(timeout, async {
+            loop {
+                match self.get_status(project_id) {
+                    ProcessStatus::Running | ProcessStatus::Healthy => return Ok(()),
+                    ProcessStatus::Stopped | ProcessStatus::Error | ProcessStatus::Cleanup => {
+                        return Err("Dependency exited before becoming ready".to_string());
                     }
+                    ProcessStatus::Setup
+                    | ProcessStatus::Starting
+                    | ProcessStatus::Unhealthy
+                    | ProcessStatus::Restarting
+                    | ProcessStatus::Backoff => {}
                 }
+                // The bus lives as long as the manager, so this only waits
+                let _ = changes.changed().await;
+            }
+        })
+        .await;
+        ready.unwrap_or_else(|_| Err(format!("Dependency not ready after {}s", timeout.as_secs())))
+    }
+
+    /// Add a system line to a project's log and emit it. A project with
+    /// services has no log of its own, so each service gets the line.
+    pub fn add_system_log(&self, project: &Project, message: String) {
+        self.add_system_entry(project, LogLevel::Info, message);
+    }
+
+    /// Log a failure the same way
+    pub fn add_system_error(&self, project: &Project, message: String) {
+        self.add_system_entry(project, LogLevel::Error, message);
+    }
+
+    fn add_system_entry(&self, project: &Project, level: LogLevel, message: String) {
+        for unit in project.units() {
+            let entry = {
+                let mut procs = self.processes.lock().unwrap();
+                procs
+                    .entry(unit.id.clone())
+                    .or_insert_with(|| ProcessInfo::new(unit.clone(), self.log_store.clone()))
+                    .add_entry(LogStream::System, level, message.clone())
+            };
+            Self::emit_logs(&self.events, &unit.id, vec![entry]);
+        }
+    }
+
+    /// Internal spawn process (used for initial start and restarts).
+    /// With `setup`, the project's setup steps run first on the
+    /// supervisor task and the main command only starts if they succeed.
+    /// A `before_start` hook also moves the start onto that task.
+    fn spawn_process(&self, project: &Project, restart_count: u32, setup: bool) -> Result<(), String> {
+        self.close_breaker(&project.id);
+        let run_setup = setup && !project.setup.is_empty();
+        let before_start = HookEvent::BeforeStart.command(&project.hooks).is_some();
+        if !run_setup && !before_start {
+            let run = self.launch(project, restart_count)?;
+            tauri::async_runtime::spawn(self.clone().supervise(project.id.clone(), run));
+            return Ok(());
+        }
+
+        // Report a bad path to the caller rather than as a failed step
+        if !std::path::Path::new(&project.path).is_dir() {
+            return Err(format!("Project path does not exist: {}", project.path));
+        }
+        let manager = self.clone();
+        let project = project.clone();
+        if !run_setup {
+            let start = self.begin_start(&project);
+            tauri::async_runtime::spawn(async move {
+                manager.start_after_hook(&project, restart_count, start).await;
             });
+            return Ok(());
         }
 
-        // Spawn monitoring thread for crash detection
-        let processes_monitor = Arc::clone(&self.processes);
-        let stdin_handles_monitor = Arc::clone(&self.stdin_handles);
-        let app_handle_monitor = Arc::clone(&self.app_handle);
-        let git_bash_path = self.git_bash_path.clone();
-        let pid_monitor = pid.clone();
-
-        thread::spawn(move || {
-            Self::monitor_process(
-                processes_monitor,
-                stdin_handles_monitor,
-                app_handle_monitor,
-                git_bash_path,
-                pid_monitor,
-            );
+        let stage = self.begin_stage(&project, Stage::Setup);
+        tauri::async_runtime::spawn(async move {
+            match manager.run_stage(&project, stage).await {
+                StageResult::Done(run_id) => {
+                    if !manager.is_current(&project.id, run_id, &ProcessStatus::Setup) {
+                        return;
+                    }
+                    if before_start {
+                        let start = manager.begin_start(&project);
+                        manager.start_after_hook(&project, restart_count, start).await;
+                    } else {
+                        manager.launch_and_supervise(&project, restart_count).await;
+                    }
+                }
+                StageResult::Failed => {}
+                StageResult::Stopped { reply, outcome } => {
+                    manager.finish_stop(&project.id, reply, outcome).await;
+                }
+            }
         });
-
         Ok(())
     }
 
-    /// Monitor process for crashes and auto-restart
-    fn monitor_process(
-        processes: Arc<Mutex<HashMap<String, ProcessInfo>>>,
-        stdin_handles: Arc<Mutex<HashMap<String, ChildStdin>>>,
-        app_handle: Arc<Mutex<Option<AppHandle>>>,
-        git_bash_path: String,
-        project_id: String,
+    /// Mark a unit as starting while its `before_start` hook runs, so it
+    /// counts as active. A stop meanwhile waits for the hook, then cancels
+    /// the start.
+    fn begin_start(&self, project: &Project) -> (u64, mpsc::UnboundedReceiver<RunControl>) {
+        let (control_tx, control) = mpsc::unbounded_channel();
+        let run_id = {
+            let mut procs = self.processes.lock().unwrap();
+            let info = procs
+                .entry(project.id.clone())
+                .or_insert_with(|| ProcessInfo::new(project.clone(), self.log_store.clone()));
+            info.status = ProcessStatus::Starting;
+            info.control = Some(control_tx);
+            info.project = project.clone();
+            info.step = None;
+            info.run_id += 1;
+            info.run_id
+        };
+        self.emit_status(&project.id, &ProcessStatus::Starting);
+        (run_id, control)
+    }
+
+    /// Run the `before_start` hook of a unit marked by `begin_start`, then
+    /// launch its main command unless it was stopped meanwhile
+    async fn start_after_hook(
+        &self,
+        project: &Project,
+        restart_count: u32,
+        (run_id, control): (u64, mpsc::UnboundedReceiver<RunControl>),
     ) {
-        loop {
-            thread::sleep(Duration::from_millis(500));
+        self.run_hook(project, HookRun::new(HookEvent::BeforeStart, None, restart_count))
+            .await;
+        // Dropping the channel answers a pending stop
+        drop(control);
+        if self.is_current(&project.id, run_id, &ProcessStatus::Starting) {
+            self.launch_and_supervise(project, restart_count).await;
+        }
+    }
 
-            let should_restart;
-            let restart_count;
-            let path;
-            let commands;
+    async fn launch_and_supervise(&self, project: &Project, restart_count: u32) {
+        match self.launch(project, restart_count) {
+            Ok(run) => self.clone().supervise(project.id.clone(), run).await,
+            Err(e) => self.fail_start(&project.id, format!("Failed to start: {}", e)),
+        }
+    }
 
-            {
-                let mut procs = processes.lock().unwrap();
-                let info = match procs.get_mut(&project_id) {
-                    Some(info) => info,
-                    None => return, // Process info removed, exit monitor
-                };
+    /// Run one of a unit's hooks, if configured, logging its command,
+    /// output and exit code under a `[hook <name>]` marker. A hook still
+    /// running after `hooks.timeout_ms` is killed with its process group.
+    /// Failures are only logged; they never affect the unit.
+    async fn run_hook(&self, project: &Project, hook: HookRun) {
+        let Some(command) = hook.event.command(&project.hooks) else {
+            return;
+        };
+        let marker = format!("[hook {}]", hook.event.name());
+        let default_shell = *self.default_shell.lock().unwrap();
+        // Env file problems are reported by the start itself
+        let (mut env, _) = env_file::resolve_project_env(project);
+        env.extend(hook.env(project));
+
+        let hook_project = Project {
+            commands: vec![command.to_string()],
+            pty: None,
+            ..project.clone()
+        };
+        let spawned = match Self::spawn_child(&hook_project, default_shell, &env, None) {
+            Ok(spawned) => spawned,
+            Err(e) => {
+                Self::push_error(&self.processes, &self.events, &project.id, format!("{} Failed to start: {}", marker, e));
+                return;
+            }
+        };
+        let Spawned { mut child, pid, mut output, command_line, .. } = spawned;
+        Self::push_log(&self.processes, &self.events, &project.id, format!("{} $ {}", marker, command_line));
+
+        let timeout = Duration::from_millis(project.hooks.timeout_ms.max(100));
+        let (lines, writer) = self.log_writer(&project.id);
+        let finished = tokio::time::timeout(timeout, async {
+            if let ProcessOutput::Pipes(stdout, stderr) = &mut output {
+                tokio::join!(
+                    Self::read_lines(&lines, Some(&marker), LogStream::Stdout, stdout.as_mut()),
+                    Self::read_lines(&lines, Some(&marker), LogStream::Stderr, stderr.as_mut()),
+                );
+            }
+            child.wait().await
+        })
+        .await;
+        drop(lines);
+        let _ = writer.await;
+
+        let message = match finished {
+            Ok(Ok(status)) if status.success() => Ok(format!("{} Exited with code 0", marker)),
+            Ok(Ok(status)) => Err(format!("{} Failed with {}", marker, ExitDetails::from_status(&status))),
+            Ok(Err(e)) => Err(format!("{} Failed to check hook status: {}", marker, e)),
+            Err(_) => {
+                process_group::kill_tree(pid);
+                let _ = child.wait().await;
+                Err(format!("{} Timed out after {}ms, killed", marker, timeout.as_millis()))
+            }
+        };
+        match message {
+            Ok(message) => Self::push_log(&self.processes, &self.events, &project.id, message),
+            Err(message) => Self::push_error(&self.processes, &self.events, &project.id, message),
+        }
+    }
 
-                // Check if process is still running
-                if info.status != ProcessStatus::Running {
-                    return; // Not running, exit monitor
-                }
-
-                if let Some(ref mut child) = info.child {
-                    match child.try_wait() {
-                        Ok(Some(status)) => {
-                            // Process exited
-                            let exit_code = status.code().unwrap_or(-1);
-                            let timestamp = chrono::Local::now().format("%H:%M:%S").to_string();
-                            
-                            if exit_code == 0 {
-                                // Normal exit
-                                info.add_log(format!("[{}] Process exited normally", timestamp));
-                                info.status = ProcessStatus::Stopped;
-                                
-                                // Emit status
-                                if let Some(handle) = app_handle.lock().unwrap().as_ref() {
-                                    let _ = handle.emit("process-status", StatusPayload {
-                                        project_id: project_id.clone(),
-                                        status: "stopped".to_string(),
-                                    });
-                                }
-                                return;
-                            } else {
-                                // Crashed
-                                info.add_log(format!("[{}] [ERR] Process crashed with exit code: {}", timestamp, exit_code));
-                                
-                                should_restart = info.restart_on_crash && info.restart_count < MAX_RESTART_ATTEMPTS;
-                                restart_count = info.restart_count + 1;
-                                path = info.path.clone();
-                                commands = info.commands.clone();
-
-                                // Emit crash event
-                                if let Some(handle) = app_handle.lock().unwrap().as_ref() {
-                                    let _ = handle.emit("process-crash", CrashPayload {
-                                        project_id: project_id.clone(),
-                                        restart_count,
-                                        will_restart: should_restart,
-                                    });
-                                }
+    /// Whether `run_id` is still the latest run of a unit and in `status`
+    fn is_current(&self, project_id: &str, run_id: u64, status: &ProcessStatus) -> bool {
+        let procs = self.processes.lock().unwrap();
+        procs
+            .get(project_id)
+            .is_some_and(|info| info.run_id == run_id && info.status == *status)
+    }
 
-                                if should_restart {
-                                    info.status = ProcessStatus::Restarting;
-                                    info.add_log(format!("[{}] Restarting... (attempt {}/{})", timestamp, restart_count, MAX_RESTART_ATTEMPTS));
-                                    
-                                    if let Some(handle) = app_handle.lock().unwrap().as_ref() {
-                                        let _ = handle.emit("process-status", StatusPayload {
-                                            project_id: project_id.clone(),
-                                            status: "restarting".to_string(),
-                                        });
-                                    }
-                                } else {
-                                    info.status = ProcessStatus::Error;
-                                    if info.restart_count >= MAX_RESTART_ATTEMPTS {
-                                        info.add_log(format!("[{}] [ERR] Max restart attempts reached. Giving up.", timestamp));
-                                    }
-                                    
-                                    if let Some(handle) = app_handle.lock().unwrap().as_ref() {
-                                        let _ = handle.emit("process-status", StatusPayload {
-                                            project_id: project_id.clone(),
-                                            status: "error".to_string(),
-                                        });
-                                    }
-                                    return;
-                                }
-                            }
-                        }
-                        Ok(None) => {
-                            // Still running
-                            continue;
-                        }
-                        Err(e) => {
-                            info.add_log(format!("[ERR] Failed to check process status: {}", e));
-                            info.status = ProcessStatus::Error;
-                            return;
-                        }
+    /// Record that the main command could not be spawned
+    fn fail_start(&self, project_id: &str, message: String) {
+        {
+            let mut procs = self.processes.lock().unwrap();
+            if let Some(info) = procs.get_mut(project_id) {
+                info.status = ProcessStatus::Error;
+                info.pid = None;
+                info.control = None;
+            }
+        }
+        Self::push_error(&self.processes, &self.events, project_id, message);
+        self.emit_status(project_id, &ProcessStatus::Error);
+    }
+
+    /// Mark a unit as running its setup or cleanup steps, giving it a new
+    /// run id and control channel so it can be stopped meanwhile
+    fn begin_stage(&self, project: &Project, stage: Stage) -> StageRun {
+        let (control_tx, control) = mpsc::unbounded_channel();
+        let run_id = {
+            let mut procs = self.processes.lock().unwrap();
+            let info = procs
+                .entry(project.id.clone())
+                .or_insert_with(|| ProcessInfo::new(project.clone(), self.log_store.clone()));
+            info.status = stage.status();
+            info.control = Some(control_tx);
+            info.project = project.clone();
+            info.run_id += 1;
+            info.run_id
+        };
+        self.emit_status(&project.id, &stage.status());
+        StageRun { stage, run_id, control }
+    }
+
+    /// Run the steps of a stage one by one, logging each command and its
+    /// exit code. A failed setup step leaves the unit in `Error` with the
+    /// step recorded; cleanup carries on after a failed step.
+    async fn run_stage(&self, project: &Project, mut stage: StageRun) -> StageResult {
+        let steps = match stage.stage {
+            Stage::Setup => &project.setup,
+            Stage::Cleanup => &project.cleanup,
+        };
+        let name = stage.stage.name();
+        let default_shell = *self.default_shell.lock().unwrap();
+        let (env, env_problems) = env_file::resolve_project_env(project);
+        for problem in env_problems {
+            Self::push_error(&self.processes, &self.events, &project.id, problem);
+        }
+
+        let mut failed = false;
+        for (index, step) in steps.iter().enumerate() {
+            let label = format!("{} {}/{}", name, index + 1, steps.len());
+            {
+                let mut procs = self.processes.lock().unwrap();
+                match procs.get_mut(&project.id) {
+                    Some(info) if info.run_id == stage.run_id => {
+                        info.step = Some(format!("{}: {}", label, step));
                     }
+                    _ => return StageResult::Failed,
+                }
+            }
+            self.emit_status(&project.id, &stage.stage.status());
+
+            // Steps read no input and never use a terminal
+            let step_project = Project {
+                commands: vec![step.clone()],
+                pty: None,
+                ..project.clone()
+            };
+            let mut spawned = match Self::spawn_child(&step_project, default_shell, &env, None) {
+                Ok(spawned) => spawned,
+                Err(e) => {
+                    Self::push_error(&self.processes, &self.events, &project.id, format!("{} step failed to start: {}", label, e));
+                    failed = true;
+                    break;
+                }
+            };
+            drop(spawned.input.take());
+            {
+                let mut procs = self.processes.lock().unwrap();
+                if let Some(info) = procs.get_mut(&project.id) {
+                    info.pid = Some(spawned.pid);
+                }
+            }
+            Self::push_log(&self.processes, &self.events, &project.id, format!("[{}] $ {}", label, spawned.command_line));
+
+            let exit = self
+                .await_exit(&project.id, spawned.pid, &mut spawned.child, &mut spawned.output, &mut stage.control)
+                .await;
+            {
+                let mut procs = self.processes.lock().unwrap();
+                if let Some(info) = procs.get_mut(&project.id) {
+                    info.pid = None;
+                }
+            }
+            match exit {
+                Exit::Stopped { reply, outcome } => return StageResult::Stopped { reply, outcome },
+                Exit::Exited(Ok(status)) if status.success() => {
+                    let message = format!("[{}] Exited with code 0", label);
+                    Self::push_log(&self.processes, &self.events, &project.id, message);
+                }
+                Exit::Exited(Ok(status)) => {
+                    failed = true;
+                    let message = format!("[{}] Failed with {}", label, ExitDetails::from_status(&status));
+                    Self::push_error(&self.processes, &self.events, &project.id, message);
+                }
+                Exit::Exited(Err(e)) => {
+                    failed = true;
+                    let message = format!("[{}] Failed to check process status: {}", label, e);
+                    Self::push_error(&self.processes, &self.events, &project.id, message);
+                }
+            }
+            if failed && stage.stage == Stage::Setup {
+                break;
+            }
+        }
+
+        if failed && stage.stage == Stage::Setup {
+            {
+                let mut procs = self.processes.lock().unwrap();
+                match procs.get_mut(&project.id) {
+                    Some(info) if info.run_id == stage.run_id => {
+                        info.status = ProcessStatus::Error;
+                        info.control = None;
+                    }
+                    _ => return StageResult::Failed,
+                }
+            }
+            Self::push_error(&self.processes, &self.events, &project.id, "Setup failed, not starting".to_string());
+            self.emit_status(&project.id, &ProcessStatus::Error);
+            return StageResult::Failed;
+        }
+
+        let mut procs = self.processes.lock().unwrap();
+        if let Some(info) = procs.get_mut(&project.id) {
+            if info.run_id == stage.run_id {
+                info.step = None;
+            }
+        }
+        StageResult::Done(stage.run_id)
+    }
+
+    /// Run the cleanup steps, if any, after a unit's last run ended and
+    /// leave it in `status`
+    async fn clean_up(&self, project: &Project, status: ProcessStatus) {
+        if project.cleanup.is_empty() {
+            return;
+        }
+        let stage = self.begin_stage(project, Stage::Cleanup);
+        let run_id = match self.run_stage(project, stage).await {
+            StageResult::Done(run_id) => run_id,
+            StageResult::Failed => return,
+            // Stopped again while cleaning up; the second stop is answered right away
+            StageResult::Stopped { reply, outcome } => {
+                Self::push_log(&self.processes, &self.events, &project.id, outcome.to_string());
+                let _ = reply.send(outcome);
+                return;
+            }
+        };
+
+        {
+            let mut procs = self.processes.lock().unwrap();
+            match procs.get_mut(&project.id) {
+                Some(info) if info.run_id == run_id && info.status == ProcessStatus::Cleanup => {
+                    info.status = status.clone();
+                    info.control = None;
+                }
+                _ => return,
+            }
+        }
+        self.emit_status(&project.id, &status);
+    }
+
+    /// Complete a stop request handled by a supervisor: log how the process
+    /// ended, run the `after_stop` hook and the cleanup steps, then answer
+    /// the caller of `stop`
+    async fn finish_stop(&self, project_id: &str, reply: oneshot::Sender<StopOutcome>, outcome: StopOutcome) {
+        Self::push_log(&self.processes, &self.events, project_id, outcome.to_string());
+        let unit = {
+            let procs = self.processes.lock().unwrap();
+            procs.get(project_id).map(|info| (info.project.clone(), info.restart_count))
+        };
+        if let Some((project, restart_count)) = unit {
+            self.run_hook(&project, HookRun::new(HookEvent::AfterStop, None, restart_count))
+                .await;
+            self.clean_up(&project, ProcessStatus::Stopped).await;
+        }
+        let _ = reply.send(outcome);
+    }
+
+    /// Spawn one run of a project and record it in the process table.
+    ///
+    /// This is the single spawn path for first starts and restarts: it
+    /// resolves the environment, stores the input handle, logs the command
+    /// and any env or PTY problems, and starts the health monitor. The
+    /// returned run must be handed to `supervise`.
+    fn launch(&self, project: &Project, restart_count: u32) -> Result<Run, String> {
+        let project_id = project.id.as_str();
+        let default_shell = *self.default_shell.lock().unwrap();
+        let terminal_size = self
+            .processes
+            .lock()
+            .unwrap()
+            .get(project_id)
+            .and_then(|info| info.terminal_size);
+
+        // Re-read env files on every spawn so edits apply on the next start
+        let (env, env_problems) = env_file::resolve_project_env(project);
+        let spawned = Self::spawn_child(project, default_shell, &env, terminal_size)?;
+
+        // Store stdin handle separately so writes do not hold the process table lock
+        {
+            let mut stdin_handles = self.stdin_handles.lock().unwrap();
+            match spawned.input {
+                Some(input) => stdin_handles.insert(project_id.to_string(), input),
+                None => stdin_handles.remove(project_id),
+            };
+        }
+
+        // Create or update process info
+        let (control_tx, control) = mpsc::unbounded_channel();
+        let status = ProcessStatus::initial(project);
+        let (run_id, logs) = {
+            let mut procs = self.processes.lock().unwrap();
+            let info = procs
+                .entry(project_id.to_string())
+                .or_insert_with(|| ProcessInfo::new(project.clone(), self.log_store.clone()));
+            info.status = status.clone();
+            info.pid = Some(spawned.pid);
+            info.control = Some(control_tx);
+            info.restart_count = restart_count;
+            info.project = project.clone();
+            info.run_id += 1;
+            info.liveness_failed = false;
+            info.started_at = Some(Instant::now());
+            info.limits_hit.clear();
+            info.step = None;
+
+            let command_log = info.add_log(LogStream::System, format!("$ {}", spawned.command_line));
+            let problems: Vec<String> = env_problems.into_iter().chain(spawned.warnings).collect();
+            let logs = std::iter::once(command_log)
+                .chain(Self::log_problems(info, &problems))
+                .collect::<Vec<_>>();
+            (info.run_id, logs)
+        };
+
+        Self::emit_logs(&self.events, project_id, logs);
+
+        // Emit status changed event
+        self.emit_status(project_id, &status);
+
+        self.spawn_health_monitor(project.clone(), project.shell.unwrap_or(default_shell), run_id);
+
+        if HookEvent::AfterStart.command(&project.hooks).is_some() {
+            let manager = self.clone();
+            let project = project.clone();
+            tauri::async_runtime::spawn(async move {
+                let hook = HookRun::new(HookEvent::AfterStart, None, restart_count);
+                manager.run_hook(&project, hook).await;
+            });
+        }
+
+        Ok(Run {
+            run_id,
+            pid: spawned.pid,
+            child: spawned.child,
+            output: spawned.output,
+            control,
+        })
+    }
+
+    /// Supervise a project until it stops for good.
+    ///
+    /// Each run's output is logged while waiting for the process to exit or
+    /// for a stop or kill request. After an exit the crash, give-up or stop
+    /// hooks run and the restart policy decides whether to respawn through
+    /// `launch`, after a backoff delay; once the project is done the cleanup
+    /// steps run.
+    async fn supervise(self, project_id: String, mut run: Run) {
+        loop {
+            let exit = self
+                .await_exit(&project_id, run.pid, &mut run.child, &mut run.output, &mut run.control)
+                .await;
+            let exit = match exit {
+                Exit::Exited(exit) => exit,
+                Exit::Stopped { reply, outcome } => {
+                    self.finish_stop(&project_id, reply, outcome).await;
+                    return;
+                }
+            };
+
+            let (project, waiting, restart_count, restart_delay) = match self.handle_exit(&project_id, &run, exit) {
+                AfterExit::Restart { project, status, restart_count, delay, hooks } => {
+                    for hook in hooks {
+                        self.run_hook(&project, hook).await;
+                    }
+                    (project, status, restart_count, delay)
+                }
+                AfterExit::Done { project, status, hooks } => {
+                    for hook in hooks {
+                        self.run_hook(&project, hook).await;
+                    }
+                    self.clean_up(&project, status).await;
+                    return;
+                }
+                AfterExit::Ignore => return,
+            };
+
+            tokio::time::sleep(restart_delay).await;
+
+            // The user may have stopped or restarted the project meanwhile
+            if !self.is_current(&project_id, run.run_id, &waiting) {
+                return;
+            }
+            if waiting == ProcessStatus::Backoff {
+                self.add_system_log(&project, "Cool-off over, trying again".to_string());
+                self.close_breaker(&project_id);
+            }
+
+            if project.setup_on_restart && !project.setup.is_empty() {
+                // Setup runs on a task of its own, which supervises the new run
+                if let Err(e) = self.spawn_process(&project, restart_count, true) {
+                    self.fail_start(&project_id, format!("Failed to restart: {}", e));
+                }
+                return;
+            }
+
+            if HookEvent::BeforeStart.command(&project.hooks).is_some() {
+                self.run_hook(&project, HookRun::new(HookEvent::BeforeStart, None, restart_count))
+                    .await;
+                if !self.is_current(&project_id, run.run_id, &waiting) {
+                    return;
+                }
+            }
+
+            match self.launch(&project, restart_count) {
+                Ok(next) => run = next,
+                Err(e) => {
+                    self.fail_start(&project_id, format!("Failed to restart: {}", e));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Log a process's output until it exits, handling control requests
+    /// meanwhile. A stop request ends the process tree and is returned to
+    /// be answered once any cleanup is done.
+    async fn await_exit(
+        &self,
+        project_id: &str,
+        pid: u32,
+        child: &mut Child,
+        output: &mut ProcessOutput,
+        control: &mut mpsc::UnboundedReceiver<RunControl>,
+    ) -> Exit {
+        let output = self.pump_output(project_id, output);
+        tokio::pin!(output);
+        let mut output_done = false;
+
+        let mut stopped = None;
+        let exit = loop {
+            tokio::select! {
+                status = child.wait() => break status,
+                _ = &mut output, if !output_done => output_done = true,
+                Some(control) = control.recv() => match control {
+                    RunControl::Kill => process_group::kill_tree(pid),
+                    RunControl::Stop { signal, grace, reply } => {
+                        let outcome = process_group::terminate(child, pid, signal, grace).await;
+                        stopped = Some((reply, outcome));
+                        break Ok(ExitStatus::default());
+                    }
+                },
+            }
+        };
+
+        // Collect the last lines so they are logged before the exit message
+        if !output_done {
+            let _ = tokio::time::timeout(Duration::from_millis(OUTPUT_DRAIN_MS), &mut output).await;
+        }
+
+        match stopped {
+            Some((reply, outcome)) => Exit::Stopped { reply, outcome },
+            None => Exit::Exited(exit),
+        }
+    }
+
+    /// Copy a run's output into its log until every stream is closed and
+    /// the last batch is logged
+    async fn pump_output(&self, project_id: &str, output: &mut ProcessOutput) {
+        let (lines, writer) = self.log_writer(project_id);
+        match output {
+            // Many tools write to stderr for normal output, so it is only
+            // told apart by its stream, not marked as an error
+            ProcessOutput::Pipes(stdout, stderr) => {
+                tokio::join!(
+                    Self::read_lines(&lines, None, LogStream::Stdout, stdout.as_mut()),
+                    Self::read_lines(&lines, None, LogStream::Stderr, stderr.as_mut()),
+                );
+            }
+            ProcessOutput::Pty(reader) => {
+                Self::read_pty_output(&lines, reader).await;
+            }
+        }
+        drop(lines);
+        let _ = writer.await;
+    }
+
+    /// Start a task logging the lines sent to it in batches; it ends once
+    /// every sender is dropped
+    fn log_writer(&self, project_id: &str) -> (mpsc::Sender<Output>, tauri::async_runtime::JoinHandle<()>) {
+        let (lines, receiver) = mpsc::channel(LOG_BATCH_LINES * 4);
+        let processes = Arc::clone(&self.processes);
+        let events = Arc::clone(&self.events);
+        let project_id = project_id.to_string();
+        let writer = tauri::async_runtime::spawn(async move {
+            Self::write_logs(&processes, &events, &project_id, receiver).await;
+        });
+        (lines, writer)
+    }
+
+    /// Log lines in batches of up to `LOG_BATCH_LINES`, or of what arrived
+    /// within `LOG_BATCH_MS` of the first, emitting one `process-log` and,
+    /// from a terminal, one `process-output` event per batch so chatty
+    /// output takes the lock and reaches the window only a few times per
+    /// second
+    async fn write_logs(
+        processes: &Mutex<HashMap<String, ProcessInfo>>,
+        events: &EventBus,
+        project_id: &str,
+        mut receiver: mpsc::Receiver<Output>,
+    ) {
+        let mut batch = Vec::with_capacity(LOG_BATCH_LINES);
+        let mut report_at: Option<Instant> = None;
+        loop {
+            // Wait for a line, or until dropped lines are due to be reported
+            let mut closed = match report_at {
+                Some(at) => {
+                    let at = tokio::time::Instant::from_std(at);
+                    let received = tokio::time::timeout_at(at, receiver.recv_many(&mut batch, LOG_BATCH_LINES));
+                    matches!(received.await, Ok(0))
+                }
+                None => receiver.recv_many(&mut batch, LOG_BATCH_LINES).await == 0,
+            };
+
+            // Collect what follows the first line for a moment
+            let deadline = tokio::time::Instant::now() + Duration::from_millis(LOG_BATCH_MS);
+            while !closed && !batch.is_empty() && batch.len() < LOG_BATCH_LINES {
+                let limit = LOG_BATCH_LINES - batch.len();
+                match tokio::time::timeout_at(deadline, receiver.recv_many(&mut batch, limit)).await {
+                    Ok(0) => closed = true,
+                    Ok(_) => {}
+                    Err(_) => break,
+                }
+            }
+
+            let (entries, raw) = {
+                let mut procs = processes.lock().unwrap();
+                let Some(info) = procs.get_mut(project_id) else {
+                    return;
+                };
+                let (mut entries, raw) = info.add_output(std::mem::take(&mut batch));
+                if closed {
+                    entries.extend(info.report_suppressed());
+                }
+                report_at = info.suppressed_until();
+                (entries, raw)
+            };
+            if !raw.is_empty() {
+                events.emit("process-output", OutputPayload {
+                    unit: UnitRef::new(project_id),
+                    data: raw,
+                });
+            }
+            Self::emit_logs(events, project_id, entries);
+            if closed {
+                return;
+            }
+        }
+    }
+
+    /// Send every line of a piped stream to the log as `kind`, after
+    /// `marker` if given; invalid UTF-8 is replaced, not dropped
+    async fn read_lines<R: AsyncRead + Unpin>(
+        lines: &mpsc::Sender<Output>,
+        marker: Option<&str>,
+        kind: LogStream,
+        stream: Option<&mut R>,
+    ) {
+        let Some(stream) = stream else {
+            return;
+        };
+        let mut reader = BufReader::new(stream);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line).await {
+                Ok(0) | Err(_) => return,
+                Ok(_) => {
+                    let text = String::from_utf8_lossy(&line);
+                    let text = text.trim_end_matches(['\r', '\n']);
+                    let text = match marker {
+                        Some(marker) => format!("{} {}", marker, text),
+                        None => text.to_string(),
+                    };
+                    if lines.send(Output::Line(kind, text)).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Send raw PTY output, ANSI sequences included, to the log writer,
+    /// followed by its lines. Returns when the last process holding the
+    /// terminal exits.
+    async fn read_pty_output(lines: &mpsc::Sender<Output>, master: &PtyReader) {
+        let mut decoder = Utf8Decoder::default();
+        let mut pending = String::new();
+        let mut buf = [0u8; 4096];
+
+        loop {
+            let n = match master.read(&mut buf).await {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                // Linux reports EIO once the slave side is closed
+                Err(_) => break,
+            };
+
+            let text = decoder.decode(&buf[..n]);
+            if text.is_empty() {
+                continue;
+            }
+            pending.push_str(&text);
+            if lines.send(Output::Raw(text)).await.is_err() {
+                return;
+            }
+            while let Some(pos) = pending.find('\n') {
+                let rest = pending.split_off(pos + 1);
+                let line = std::mem::replace(&mut pending, rest);
+                let line = line.trim_end_matches(['\r', '\n']).to_string();
+                if lines.send(Output::Line(LogStream::Stdout, line)).await.is_err() {
+                    return;
+                }
+            }
+        }
+
+        if !pending.is_empty() {
+            let _ = lines.send(Output::Line(LogStream::Stdout, pending)).await;
+        }
+    }
+
+    /// Record the end of a run and apply the restart policy
+    fn handle_exit(&self, project_id: &str, run: &Run, exit: io::Result<ExitStatus>) -> AfterExit {
+        let mut procs = self.processes.lock().unwrap();
+        let Some(info) = procs.get_mut(project_id) else {
+            return AfterExit::Ignore;
+        };
+
+        // Stopped by the user, or already replaced by a newer run
+        if info.run_id != run.run_id || !info.status.is_active() {
+            return AfterExit::Ignore;
+        }
+        info.pid = None;
+        info.control = None;
+
+        let status = match exit {
+            Ok(status) => status,
+            Err(e) => {
+                info.add_error(format!("Failed to check process status: {}", e));
+                info.status = ProcessStatus::Error;
+                let project = info.project.clone();
+                drop(procs);
+                self.emit_status(project_id, &ProcessStatus::Error);
+                return AfterExit::Done {
+                    project,
+                    status: ProcessStatus::Error,
+                    hooks: Vec::new(),
+                };
+            }
+        };
+
+        let exit = ExitDetails::from_status(&status);
+        let exit_codes = &info.project.exit_codes;
+        let failed = !exit_codes.is_success(exit.code) || info.liveness_failed;
+        let no_restart = exit_codes.prevents_restart(exit.code);
+        let mode = info.project.restart_mode();
+
+        if !failed && (mode != RestartMode::Always || no_restart) {
+            // Normal exit
+            info.add_log(LogStream::System, format!("Process exited normally with {}", exit));
+            info.status = ProcessStatus::Stopped;
+            let project = info.project.clone();
+            let hook = HookRun::new(HookEvent::AfterStop, Some(exit), info.restart_count);
+            drop(procs);
+            self.emit_status(project_id, &ProcessStatus::Stopped);
+            return AfterExit::Done {
+                project,
+                status: ProcessStatus::Stopped,
+                hooks: vec![hook],
+            };
+        }
+
+        if info.liveness_failed {
+            info.add_error("Process killed after failing its liveness probe".to_string());
+        } else if failed {
+            info.add_error(format!("Process crashed with {}", exit));
+        } else {
+            info.add_log(LogStream::System, format!("Process exited normally with {}", exit));
+        }
+
+        // A run that stayed up long enough starts a fresh series of attempts
+        let policy = info.project.restart_policy.clone();
+        let uptime = info.started_at.map(|t| t.elapsed()).unwrap_or_default();
+        if info.restart_count > 0 && uptime >= Duration::from_secs(policy.reset_after_secs) {
+            info.add_log(LogStream::System, format!("Ran for {}s, resetting restart counter", uptime.as_secs()));
+            info.restart_count = 0;
+        }
+
+        if failed {
+            info.record_crash(&exit);
+        }
+
+        let attempt = info.restart_count + 1;
+        let restartable = mode != RestartMode::Never && !no_restart;
+        let mut should_restart = restartable && policy.allows_attempt(attempt);
+        let mut restart_count = attempt;
+        let mut restart_delay = policy.delay_for(attempt);
+
+        // A crash loop, or running out of attempts, backs off instead of
+        // giving up when the project has a circuit breaker
+        let mut breaker_event = None;
+        if let Some(breaker) = policy.circuit_breaker.as_ref().filter(|_| restartable) {
+            let crashes = info.recent_crashes(Duration::from_secs(breaker.window_secs));
+            let crash_loop = failed && crashes >= breaker.max_crashes as usize;
+            if crash_loop || !should_restart {
+                restart_delay = Duration::from_secs(breaker.cool_off_secs);
+                should_restart = true;
+                restart_count = 0;
+                let reason = if crash_loop {
+                    format!("{} crashes within {}s", crashes, breaker.window_secs)
                 } else {
-                    return; // No child process
-                }
-            }
-
-            // Restart the process (outside lock)
-            if should_restart {
-                thread::sleep(Duration::from_millis(RESTART_DELAY_MS));
-                
-                // Respawn
-                let cd_command = format!("cd '{}'", path.replace('\\', "/"));
-                let full_commands: Vec<String> = std::iter::once(cd_command)
-                    .chain(commands.iter().cloned())
-                    .collect();
-                let script = full_commands.join(" && ");
-
-                match Command::new(&git_bash_path)
-                    .args(["-c", &script])
-                    .stdin(Stdio::piped())
-                    .stdout(Stdio::piped())
-                    .stderr(Stdio::piped())
-                    .creation_flags(0x08000000)
-                    .spawn()
-                {
-                    Ok(mut child) => {
-                        let stdin = child.stdin.take();
-                        let stdout = child.stdout.take();
-                        let stderr = child.stderr.take();
-
-                        // Store stdin handle for the restarted process
-                        if let Some(stdin_handle) = stdin {
-                            let mut stdin_map = stdin_handles.lock().unwrap();
-                            stdin_map.insert(project_id.clone(), stdin_handle);
-                        }
+                    "Max restart attempts reached".to_string()
+                };
+                info.add_error(format!("{}. Backing off for {}s", reason, breaker.cool_off_secs));
+                info.status = ProcessStatus::Backoff;
+                info.breaker = BreakerState::Open;
+                info.breaker_trips += 1;
+            }
+        }
 
-                        {
-                            let mut procs = processes.lock().unwrap();
-                            if let Some(info) = procs.get_mut(&project_id) {
-                                info.child = Some(child);
-                                info.status = ProcessStatus::Running;
-                                info.restart_count = restart_count;
-                                
-                                let timestamp = chrono::Local::now().format("%H:%M:%S").to_string();
-                                info.add_log(format!("[{}] Process restarted successfully", timestamp));
-                            }
-                        }
+        let next_retry_at = should_restart.then(|| {
+            let delay = chrono::Duration::from_std(restart_delay).unwrap_or_default();
+            (chrono::Local::now() + delay).to_rfc3339()
+        });
 
-                        if let Some(handle) = app_handle.lock().unwrap().as_ref() {
-                            let _ = handle.emit("process-status", StatusPayload {
-                                project_id: project_id.clone(),
-                                status: "running".to_string(),
-                            });
-                        }
+        if info.breaker == BreakerState::Open {
+            info.next_attempt_at = next_retry_at.clone();
+            breaker_event = Some(info.breaker_payload(project_id));
+        } else if should_restart {
+            info.status = ProcessStatus::Restarting;
+            let attempts = match policy.max_attempts {
+                Some(max) => format!("{}/{}", attempt, max),
+                None => attempt.to_string(),
+            };
+            info.add_log(
+                LogStream::System,
+                format!("Restarting in {:.1}s... (attempt {})", restart_delay.as_secs_f64(), attempts),
+            );
+        } else {
+            info.status = ProcessStatus::Error;
+            if no_restart {
+                info.add_log(LogStream::System, format!("Not restarting after {}", exit));
+            } else if mode != RestartMode::Never {
+                info.add_error("Max restart attempts reached. Giving up.".to_string());
+            }
+        }
+        let gave_up = !should_restart && !no_restart && mode != RestartMode::Never;
+        let mut hooks = Vec::new();
+        if failed {
+            hooks.push(HookRun::new(HookEvent::Crash, Some(exit), info.restart_count));
+        }
+        if gave_up {
+            hooks.push(HookRun::new(HookEvent::GiveUp, Some(exit), info.restart_count));
+        }
+        let project = info.project.clone();
+        let status = info.status.clone();
+        drop(procs);
+
+        // Emit crash event
+        self.emit_event("process-crash", CrashPayload {
+            unit: UnitRef::new(project_id),
+            restart_count: attempt,
+            exit_code: exit.code,
+            signal: exit.signal_name(),
+            core_dumped: exit.core_dumped,
+            will_restart: should_restart,
+            retry_delay_ms: should_restart.then_some(restart_delay.as_millis() as u64),
+            next_retry_at,
+        });
+        if let Some(payload) = breaker_event {
+            self.emit_event("process-breaker", payload);
+        }
+        self.emit_status(project_id, &status);
+
+        if should_restart {
+            AfterExit::Restart {
+                project,
+                status,
+                restart_count,
+                delay: restart_delay,
+                hooks,
+            }
+        } else {
+            AfterExit::Done { project, status, hooks }
+        }
+    }
 
-                        // Setup new stdout/stderr readers
-                        if let Some(stdout) = stdout {
-                            let processes = Arc::clone(&processes);
-                            let app_handle = Arc::clone(&app_handle);
-                            let pid = project_id.clone();
-                            
-                            thread::spawn(move || {
-                                let reader = BufReader::new(stdout);
-                                for line in reader.lines() {
-                                    if let Ok(line) = line {
-                                        let timestamp = chrono::Local::now().format("%H:%M:%S").to_string();
-                                        let log_line = format!("[{}] {}", timestamp, line);
-                                        
-                                        {
-                                            let mut procs = processes.lock().unwrap();
-                                            if let Some(info) = procs.get_mut(&pid) {
-                                                info.add_log(log_line.clone());
-                                            }
-                                        }
-
-                                        if let Some(handle) = app_handle.lock().unwrap().as_ref() {
-                                            let _ = handle.emit("process-log", LogPayload {
-                                                project_id: pid.clone(),
-                                                log: log_line,
-                                            });
-                                        }
-                                    }
-                                }
-                            });
-                        }
+    /// Run readiness and liveness probes for one run of a project.
+    ///
+    /// Moves the project from Starting to Healthy once readiness passes (or,
+    /// without a readiness probe, once liveness first passes), marks it
+    /// Unhealthy while liveness fails, and kills it after `failure_threshold`
+    /// consecutive liveness failures so `supervise` restarts it like a
+    /// crash. The task sleeps until a probe is due and ends with the run.
+    fn spawn_health_monitor(&self, project: Project, shell: ShellProfile, run_id: u64) {
+        if project.health.is_empty() {
+            return;
+        }
+        let manager = self.clone();
+        tauri::async_runtime::spawn(async move {
+            manager.monitor_health(project, shell, run_id).await;
+        });
+    }
 
-                        if let Some(stderr) = stderr {
-                            let processes = Arc::clone(&processes);
-                            let app_handle = Arc::clone(&app_handle);
-                            let pid = project_id.clone();
-                            
-                            thread::spawn(move || {
-                                let reader = BufReader::new(stderr);
-                                for line in reader.lines() {
-                                    if let Ok(line) = line {
-                                        let timestamp = chrono::Local::now().format("%H:%M:%S").to_string();
-                                        let log_line = format!("[{}] [ERR] {}", timestamp, line);
-                                        
-                                        {
-                                            let mut procs = processes.lock().unwrap();
-                                            if let Some(info) = procs.get_mut(&pid) {
-                                                info.add_log(log_line.clone());
-                                            }
-                                        }
-
-                                        if let Some(handle) = app_handle.lock().unwrap().as_ref() {
-                                            let _ = handle.emit("process-log", LogPayload {
-                                                project_id: pid.clone(),
-                                                log: log_line,
-                                            });
-                                        }
-                                    }
-                                }
-                            });
-                        }
+    async fn monitor_health(&self, project: Project, shell: ShellProfile, run_id: u64) {
+        let processes = &self.processes;
+        let project_id = project.id.as_str();
+        let log = |message: String| Self::push_log(processes, &self.events, project_id, message);
+        let error = |message: String| Self::push_error(processes, &self.events, project_id, message);
+        let set_status = |from: &[ProcessStatus], to: ProcessStatus| {
+            Self::transition(processes, &self.events, project_id, run_id, from, to)
+        };
+        let current = || {
+            processes
+                .lock()
+                .unwrap()
+                .get(project_id)
+                .is_some_and(|info| info.run_id == run_id && info.status.is_active())
+        };
+        let mut changes = self.events.watch_status();
+
+        let (env, _) = env_file::resolve_project_env(&project);
+        let cursor = processes
+            .lock()
+            .unwrap()
+            .get(project_id)
+            .map(|info| info.next_seq)
+            .unwrap_or(0);
+
+        let build = |probe: &Option<Probe>, kind: &str| {
+            probe.as_ref().and_then(|probe| {
+                ProbeRunner::new(probe, shell, cursor)
+                    .map_err(|e| error(format!("{} probe disabled: {}", kind, e)))
+                    .ok()
+            })
+        };
+        let mut readiness = build(&project.health.readiness, "Readiness");
+        let mut liveness = build(&project.health.liveness, "Liveness");
+        let mut ready = false;
 
-                        // Continue monitoring
+        loop {
+            let due = readiness
+                .as_ref()
+                .filter(|_| !ready)
+                .into_iter()
+                .chain(liveness.as_ref())
+                .map(ProbeRunner::next_at)
+                .min();
+            let Some(due) = due else {
+                return;
+            };
+            // Wake early when the run may have ended
+            tokio::select! {
+                _ = tokio::time::sleep_until(due.into()) => {}
+                _ = changes.changed() => {}
+            }
+            if !current() {
+                return;
+            }
+
+            let new_lines = |cursor| Self::log_lines_since(processes, project_id, cursor);
+
+            if !ready {
+                if let Some(runner) = readiness.as_mut() {
+                    match runner.poll(&project.path, &env, new_lines).await {
+                        Some(Ok(())) => {
+                            ready = true;
+                            log("Readiness probe passed".to_string());
+                            set_status(&[ProcessStatus::Starting], ProcessStatus::Healthy);
+                        }
+                        // Only report the first failure to keep the log readable
+                        Some(Err(e)) if runner.failures() == 1 => {
+                            log(format!("Waiting for readiness probe: {}", e));
+                        }
+                        _ => {}
                     }
-                    Err(e) => {
+                }
+            }
+
+            let Some(runner) = liveness.as_mut() else {
+                continue;
+            };
+            match runner.poll(&project.path, &env, new_lines).await {
+                Some(Ok(())) if readiness.is_none() || ready => {
+                    set_status(
+                        &[ProcessStatus::Starting, ProcessStatus::Unhealthy],
+                        ProcessStatus::Healthy,
+                    );
+                }
+                Some(Err(e)) => {
+                    error(format!(
+                        "Liveness probe failed ({}/{}): {}",
+                        runner.failures(),
+                        runner.failure_threshold(),
+                        e
+                    ));
+                    set_status(&[ProcessStatus::Healthy], ProcessStatus::Unhealthy);
+
+                    if runner.failures() >= runner.failure_threshold() {
                         let mut procs = processes.lock().unwrap();
-                        if let Some(info) = procs.get_mut(&project_id) {
-                            info.status = ProcessStatus::Error;
-                            let timestamp = chrono::Local::now().format("%H:%M:%S").to_string();
-                            info.add_log(format!("[{}] [ERR] Failed to restart: {}", timestamp, e));
-                        }
-                        
-                        if let Some(handle) = app_handle.lock().unwrap().as_ref() {
-                            let _ = handle.emit("process-status", StatusPayload {
-                                project_id: project_id.clone(),
-                                status: "error".to_string(),
-                            });
+                        if let Some(info) = procs.get_mut(project_id) {
+                            if info.run_id == run_id && info.status.is_active() {
+                                info.liveness_failed = true;
+                                if let Some(control) = info.control.as_ref() {
+                                    let _ = control.send(RunControl::Kill);
+                                }
+                            }
                         }
                         return;
                     }
                 }
+                _ => {}
             }
         }
     }
 
-    /// Stop a project process
-    pub fn stop_project(&self, project_id: &str) -> Result<(), String> {
-        let mut procs = self.processes.lock().unwrap();
-        
-        if let Some(info) = procs.get_mut(project_id) {
-            if let Some(ref mut child) = info.child {
-                let pid = child.id();
-                
-                // On Windows, use taskkill to kill the entire process tree
-                #[cfg(windows)]
-                {
-                    let _ = std::process::Command::new("taskkill")
-                        .args(["/F", "/T", "/PID", &pid.to_string()])
-                        .creation_flags(0x08000000) // CREATE_NO_WINDOW
-                        .output();
-                }
-                
-                // Fallback: also try normal kill
-                let _ = child.kill();
-                let _ = child.wait(); // Wait for cleanup
+    /// Text of the lines logged from `cursor` on, with the new cursor
+    fn log_lines_since(
+        processes: &Mutex<HashMap<String, ProcessInfo>>,
+        project_id: &str,
+        cursor: u64,
+    ) -> (Vec<String>, u64) {
+        let procs = processes.lock().unwrap();
+        let Some(info) = procs.get(project_id) else {
+            return (Vec::new(), cursor);
+        };
+        let start = info.logs.partition_point(|entry| entry.seq < cursor);
+        let lines = info.logs.range(start..).map(|entry| entry.text.clone()).collect();
+        (lines, info.next_seq)
+    }
+
+    /// Change the status of a run if it is currently in one of `from`
+    fn transition(
+        processes: &Mutex<HashMap<String, ProcessInfo>>,
+        events: &EventBus,
+        project_id: &str,
+        run_id: u64,
+        from: &[ProcessStatus],
+        to: ProcessStatus,
+    ) {
+        {
+            let mut procs = processes.lock().unwrap();
+            match procs.get_mut(project_id) {
+                Some(info) if info.run_id == run_id && from.contains(&info.status) => {
+                    info.status = to.clone();
+                }
+                _ => return,
             }
+        }
+
+        Self::emit_unit_status(processes, events, project_id, &to);
+    }
+
+    /// Emit a unit's new status, followed by the combined status of its
+    /// project when the unit is a service
+    fn emit_unit_status(
+        processes: &Mutex<HashMap<String, ProcessInfo>>,
+        events: &EventBus,
+        unit_id: &str,
+        status: &ProcessStatus,
+    ) {
+        events.status_changed();
+        let unit = UnitRef::new(unit_id);
+        let mut payloads = Vec::new();
+        {
+            let procs = processes.lock().unwrap();
+            let step = procs.get(unit_id).and_then(|info| info.step.clone());
+            if unit.service.is_some() {
+                payloads.push(StatusPayload {
+                    unit: UnitRef {
+                        project_id: unit.project_id.clone(),
+                        service: None,
+                    },
+                    status: Self::combined_status(&procs, &unit.project_id).as_str().to_string(),
+                    step: None,
+                });
+            }
+            payloads.insert(0, StatusPayload {
+                unit,
+                status: status.as_str().to_string(),
+                step,
+            });
+        }
+
+        for payload in payloads {
+            events.emit("process-status", payload);
+        }
+    }
+
+    /// Close a unit's circuit breaker if it is open: when its cool-off is
+    /// over, or when the user starts or stops it
+    fn close_breaker(&self, unit_id: &str) {
+        let payload = {
+            let mut procs = self.processes.lock().unwrap();
+            let Some(info) = procs.get_mut(unit_id) else {
+                return;
+            };
+            if info.breaker != BreakerState::Open {
+                return;
+            }
+            info.close_breaker();
+            info.breaker_payload(unit_id)
+        };
+        self.emit_event("process-breaker", payload);
+    }
+
+    fn emit_status(&self, unit_id: &str, status: &ProcessStatus) {
+        Self::emit_unit_status(&self.processes, &self.events, unit_id, status);
+    }
+
+    /// Add a system line to a project's log and emit it
+    fn push_log(
+        processes: &Mutex<HashMap<String, ProcessInfo>>,
+        events: &EventBus,
+        project_id: &str,
+        message: String,
+    ) {
+        Self::push_entry(processes, events, project_id, LogStream::System, LogLevel::Info, message);
+    }
+
+    /// Add a failure to a project's log and emit it
+    fn push_error(
+        processes: &Mutex<HashMap<String, ProcessInfo>>,
+        events: &EventBus,
+        project_id: &str,
+        message: String,
+    ) {
+        Self::push_entry(processes, events, project_id, LogStream::System, LogLevel::Error, message);
+    }
+
+    /// Add a line to a project's log and emit it
+    fn push_entry(
+        processes: &Mutex<HashMap<String, ProcessInfo>>,
+        events: &EventBus,
+        project_id: &str,
+        stream: LogStream,
+        level: LogLevel,
+        text: String,
+    ) {
+        let entry = {
+            let mut procs = processes.lock().unwrap();
+            match procs.get_mut(project_id) {
+                Some(info) => info.add_entry(stream, level, text),
+                None => return,
+            }
+        };
+        Self::emit_logs(events, project_id, vec![entry]);
+    }
+
+    fn emit_logs(events: &EventBus, project_id: &str, logs: Vec<LogEntry>) {
+        if logs.is_empty() {
+            return;
+        }
+        events.emit("process-log", LogPayload {
+            unit: UnitRef::new(project_id),
+            logs,
+        });
+    }
+
+    /// Record spawn problems (env files, PTY fallback) in the project log,
+    /// returning the entries to emit
+    fn log_problems(info: &mut ProcessInfo, problems: &[String]) -> Vec<LogEntry> {
+        problems
+            .iter()
+            .map(|problem| info.add_error(problem.clone()))
+            .collect()
+    }
+
+    /// Stop a project with all of its services, or a single service.
+    /// Services stop concurrently.
+    pub async fn stop(&self, id: &str) -> Result<(), String> {
+        let keys = Self::unit_keys(&self.processes.lock().unwrap(), id);
+        let mut stops = tokio::task::JoinSet::new();
+        for key in keys {
+            let manager = self.clone();
+            stops.spawn(async move { manager.stop_unit(&key).await });
+        }
+        while stops.join_next().await.is_some() {}
+        Ok(())
+    }
+
+    /// Stop one unit.
+    ///
+    /// Asks the run's supervisor to send the project's stop signal to its
+    /// whole process group, wait up to its stop timeout, then force kill
+    /// anything left. The log records which step ended the process.
+    ///
+    /// A unit waiting to be restarted has no process left to stop, but
+    /// still gets its `after_stop` hook and cleanup steps.
+    async fn stop_unit(&self, project_id: &str) {
+        let (control, signal, grace, waiting) = {
+            let mut procs = self.processes.lock().unwrap();
+            let Some(info) = procs.get_mut(project_id) else {
+                return;
+            };
+            let waiting = matches!(info.status, ProcessStatus::Restarting | ProcessStatus::Backoff);
+
+            // Marking the project stopped first keeps the supervisor from restarting it
             info.status = ProcessStatus::Stopped;
-            info.child = None;
             info.restart_count = 0; // Reset restart count
+            info.pid = None;
+            (
+                info.control.take(),
+                info.project.stop_signal,
+                Duration::from_millis(info.project.stop_timeout_ms),
+                waiting,
+            )
+        };
+
+        self.close_breaker(project_id);
+
+        // Clear stdin handle
+        {
+            let mut stdin_handles = self.stdin_handles.lock().unwrap();
+            stdin_handles.remove(project_id);
+        }
 
-            // Clear stdin handle
-            {
-                let mut stdin_handles = self.stdin_handles.lock().unwrap();
-                stdin_handles.remove(project_id);
+        if let Some(control) = control {
+            let (reply, outcome) = oneshot::channel();
+            if control.send(RunControl::Stop { signal, grace, reply }).is_ok() {
+                // No reply means the process had exited on its own meanwhile.
+                // The supervisor logs the outcome and runs cleanup before replying.
+                let _ = outcome.await;
             }
+        } else if waiting {
+            // The supervisor sees the unit stopped once its delay is over
+            let (reply, _) = oneshot::channel();
+            self.finish_stop(project_id, reply, StopOutcome::AlreadyExited).await;
+        }
 
-            // Emit status changed
-            self.emit_event("process-status", StatusPayload {
-                project_id: project_id.to_string(),
-                status: "stopped".to_string(),
-            });
+        // Emit status changed
+        self.emit_status(project_id, &ProcessStatus::Stopped);
+    }
+
+    /// Status of a service, or the combined status of a project's units
+    pub fn get_status(&self, id: &str) -> ProcessStatus {
+        let procs = self.processes.lock().unwrap();
+        Self::combined_status(&procs, id)
+    }
+
+    /// Get process logs; before a unit first runs, the tail of its log on disk
+    pub fn get_logs(&self, project_id: &str) -> Vec<LogEntry> {
+        let procs = self.processes.lock().unwrap();
+        match procs.get(project_id) {
+            Some(info) => info.logs.iter().cloned().collect(),
+            None => self.log_store.tail(project_id, MAX_LOG_LINES),
         }
-        
-        Ok(())
     }
 
-    /// Get process status
-    pub fn get_status(&self, project_id: &str) -> ProcessStatus {
+    /// Search the logs of units in memory and, unless persisting is off,
+    /// on disk
+    pub fn search_logs(&self, unit_ids: Vec<String>, search: &Search) -> LogSearch {
+        let units = unit_ids
+            .into_iter()
+            .map(|unit_id| {
+                let in_memory: Vec<LogEntry> = {
+                    let procs = self.processes.lock().unwrap();
+                    procs
+                        .get(&unit_id)
+                        .map(|info| info.logs.iter().cloned().collect())
+                        .unwrap_or_default()
+                };
+                // Memory holds the entries the writer has not reached yet
+                let on_disk: Vec<LogEntry> = self.log_store.entries(&unit_id, search.since()).collect();
+                let last_on_disk = on_disk.iter().map(|entry| entry.seq).max();
+                let entries = on_disk.into_iter().chain(
+                    in_memory
+                        .into_iter()
+                        .filter(move |entry| last_on_disk.is_none_or(|seq| entry.seq > seq)),
+                );
+                (unit_id, entries)
+            })
+            .collect();
+        search.run(units)
+    }
+
+    /// Up to `limit` log entries before or after the sequence number
+    /// `cursor`; before a unit first runs, paged from the tail on disk
+    pub fn get_log_page(
+        &self,
+        project_id: &str,
+        cursor: Option<u64>,
+        direction: LogDirection,
+        limit: usize,
+    ) -> LogPage {
         let procs = self.processes.lock().unwrap();
-        procs
-            .get(project_id)
-            .map(|info| info.status.clone())
-            .unwrap_or(ProcessStatus::Stopped)
+        if let Some(info) = procs.get(project_id) {
+            return LogPage::read(&info.logs, info.next_seq, cursor, direction, limit);
+        }
+        drop(procs);
+        let logs: VecDeque<_> = self.log_store.tail(project_id, MAX_LOG_LINES).into();
+        let next_seq = logs.back().map_or(0, |entry| entry.seq + 1);
+        LogPage::read(&logs, next_seq, cursor, direction, limit)
     }
 
-    /// Get process logs
-    pub fn get_logs(&self, project_id: &str) -> Vec<String> {
+    /// Recent resource samples for a project, oldest first
+    pub fn get_stats(&self, project_id: &str) -> Vec<ResourceSample> {
         let procs = self.processes.lock().unwrap();
         procs
             .get(project_id)
-            .map(|info| info.logs.clone())
+            .map(|info| info.stats.iter().cloned().collect())
             .unwrap_or_default()
     }
 
+    /// Circuit breaker state and recent crashes of a unit
+    pub fn get_breaker_status(&self, unit_id: &str) -> BreakerPayload {
+        let procs = self.processes.lock().unwrap();
+        match procs.get(unit_id) {
+            Some(info) => info.breaker_payload(unit_id),
+            None => BreakerPayload {
+                unit: UnitRef::new(unit_id),
+                state: BreakerState::Closed,
+                trips: 0,
+                next_attempt_at: None,
+                crashes: Vec::new(),
+            },
+        }
+    }
+
     /// Clear logs for a project
     pub fn clear_logs(&self, project_id: &str) {
         let mut procs = self.processes.lock().unwrap();
@@ -617,134 +2486,142 @@ impl ProcessManager {
 
     /// Send input to a running process
     pub fn send_input(&self, project_id: &str, input: &str) -> Result<(), String> {
-        // Check if process is running
-        {
-            let procs = self.processes.lock().unwrap();
-            match procs.get(project_id) {
-                Some(info) if info.status == ProcessStatus::Running => {}
-                Some(_) => return Err("Process is not running".to_string()),
-                None => return Err("Project not found".to_string()),
-            }
+        let stdin = self
+            .input_handle(project_id)?
+            .ok_or("No stdin handle available for this process")?;
+
+        // A terminal sends Enter as a carriage return and echoes the input itself
+        let is_pty = matches!(stdin, ProcessInput::Pty(_));
+        let input_with_newline = format!("{}{}", input, if is_pty { "\r" } else { "\n" });
+        stdin
+            .send(input_with_newline.as_bytes())
+            .map_err(|e| format!("Failed to write to stdin: {}", e))?;
+
+        if is_pty {
+            return Ok(());
         }
 
-        // Get stdin handle and write input
-        let mut stdin_handles = self.stdin_handles.lock().unwrap();
-        if let Some(stdin) = stdin_handles.get_mut(project_id) {
-            // Write the input with a newline
-            let input_with_newline = format!("{}\n", input);
-            stdin
-                .write_all(input_with_newline.as_bytes())
-                .map_err(|e| format!("Failed to write to stdin: {}", e))?;
-            stdin
-                .flush()
-                .map_err(|e| format!("Failed to flush stdin: {}", e))?;
-
-            // Echo the input to logs
-            let timestamp = chrono::Local::now().format("%H:%M:%S").to_string();
-            let log_line = format!("[{}] > {}", timestamp, input);
-            
-            {
-                let mut procs = self.processes.lock().unwrap();
-                if let Some(info) = procs.get_mut(project_id) {
-                    info.add_log(log_line.clone());
-                }
-            }
+        // Echo the input to logs
+        Self::push_entry(&self.processes, &self.events, project_id, LogStream::Stdin, LogLevel::Info, input.to_string());
+        Ok(())
+    }
 
-            // Emit log event for the echoed input
-            self.emit_event("process-log", LogPayload {
-                project_id: project_id.to_string(),
-                log: log_line,
-            });
+    /// Send interrupt signal (Ctrl+C) to a running process
+    pub fn send_interrupt(&self, project_id: &str) -> Result<(), String> {
+        self.send_control(project_id, ControlKey::Interrupt)
+    }
 
-            Ok(())
-        } else {
-            Err("No stdin handle available for this process".to_string())
+    /// Send Ctrl+C, Ctrl+Z or Ctrl+D to a running process.
+    ///
+    /// In PTY mode the key goes through the terminal's line discipline, which
+    /// turns it into `SIGINT`, `SIGTSTP` or end of input. Without a terminal,
+    /// Ctrl+C and Ctrl+Z are sent as signals to the process group on Unix and
+    /// Ctrl+D closes stdin.
+    pub fn send_control(&self, project_id: &str, key: ControlKey) -> Result<(), String> {
+        match self.input_handle(project_id)?.ok_or("No stdin handle available")? {
+            stdin @ ProcessInput::Pty(_) => {
+                stdin
+                    .send(&[key.byte()])
+                    .map_err(|e| format!("Failed to send {}: {}", key.caret(), e))?;
+            }
+            ProcessInput::Pipe(_) if key == ControlKey::Eof => {
+                // Closing stdin is the only way to end input through a pipe
+                self.stdin_handles.lock().unwrap().remove(project_id);
+            }
+            ProcessInput::Pipe(stdin) => self.signal_piped(project_id, key, &stdin)?,
         }
+
+        Self::push_entry(&self.processes, &self.events, project_id, LogStream::Stdin, LogLevel::Info, key.caret().to_string());
+        Ok(())
     }
 
-    /// Send interrupt signal (Ctrl+C) to a running process
-    pub fn send_interrupt(&self, project_id: &str) -> Result<(), String> {
-        // Check if process is running
+    /// The input handle of a running process, cloned so writing to it does
+    /// not hold up spawns and stops of other units
+    fn input_handle(&self, project_id: &str) -> Result<Option<ProcessInput>, String> {
         {
             let procs = self.processes.lock().unwrap();
             match procs.get(project_id) {
-                Some(info) if info.status == ProcessStatus::Running => {}
+                Some(info) if info.status.is_active() => {}
                 Some(_) => return Err("Process is not running".to_string()),
                 None => return Err("Project not found".to_string()),
             }
         }
+        Ok(self.stdin_handles.lock().unwrap().get(project_id).cloned())
+    }
 
-        // Send Ctrl+C character (0x03 = ETX = End of Text)
-        let mut stdin_handles = self.stdin_handles.lock().unwrap();
-        if let Some(stdin) = stdin_handles.get_mut(project_id) {
-            // Write Ctrl+C character
-            stdin
-                .write_all(&[0x03])
-                .map_err(|e| format!("Failed to send interrupt: {}", e))?;
-            stdin
-                .flush()
-                .map_err(|e| format!("Failed to flush: {}", e))?;
+    /// Deliver Ctrl+C or Ctrl+Z to a process without a terminal
+    #[cfg(unix)]
+    fn signal_piped(&self, project_id: &str, key: ControlKey, _stdin: &Mutex<PipeWriter>) -> Result<(), String> {
+        let signal = match key {
+            ControlKey::Suspend => libc::SIGTSTP,
+            _ => libc::SIGINT,
+        };
+        let procs = self.processes.lock().unwrap();
+        let pid = procs
+            .get(project_id)
+            .and_then(|info| info.pid)
+            .ok_or("Process is not running")?;
+        process_group::signal_group(pid, signal);
+        Ok(())
+    }
 
-            // Log the interrupt
-            let timestamp = chrono::Local::now().format("%H:%M:%S").to_string();
-            let log_line = format!("[{}] ^C", timestamp);
-            
-            {
-                let mut procs = self.processes.lock().unwrap();
-                if let Some(info) = procs.get_mut(project_id) {
-                    info.add_log(log_line.clone());
-                }
-            }
+    /// Windows has no signals to send; write the raw byte for Ctrl+C instead
+    #[cfg(not(unix))]
+    fn signal_piped(&self, _project_id: &str, key: ControlKey, stdin: &Mutex<PipeWriter>) -> Result<(), String> {
+        if key == ControlKey::Suspend {
+            return Err("Ctrl+Z is not supported on this platform".to_string());
+        }
+        let mut stdin = stdin.lock().unwrap();
+        stdin
+            .write_all(&[key.byte()])
+            .and_then(|_| stdin.flush())
+            .map_err(|e| format!("Failed to send interrupt: {}", e))
+    }
 
-            self.emit_event("process-log", LogPayload {
-                project_id: project_id.to_string(),
-                log: log_line,
-            });
+    /// Resize the pseudo-terminal of a project running in PTY mode.
+    /// The size is kept for restarts of the same project.
+    pub fn resize_terminal(&self, project_id: &str, cols: u16, rows: u16) -> Result<(), String> {
+        let stdin_handles = self.stdin_handles.lock().unwrap();
+        match stdin_handles.get(project_id) {
+            Some(ProcessInput::Pty(master)) => master.resize(cols, rows)?,
+            Some(ProcessInput::Pipe(_)) => {
+                return Err("Project is not running in a pseudo-terminal".to_string());
+            }
+            None => return Err("Process is not running".to_string()),
+        }
+        drop(stdin_handles);
 
-            Ok(())
-        } else {
-            Err("No stdin handle available".to_string())
+        let mut procs = self.processes.lock().unwrap();
+        if let Some(info) = procs.get_mut(project_id) {
+            info.terminal_size = Some(PtyOptions { cols, rows });
         }
+        Ok(())
     }
 
-    /// Check if a project is running
-    #[allow(dead_code)]
-    pub fn is_running(&self, project_id: &str) -> bool {
-        self.get_status(project_id) == ProcessStatus::Running
+    /// Check if a service, or any unit of a project, is running
+    pub fn is_running(&self, id: &str) -> bool {
+        let procs = self.processes.lock().unwrap();
+        Self::unit_keys(&procs, id)
+            .iter()
+            .any(|key| procs.get(key).is_some_and(|info| info.status.is_active()))
     }
 
-    /// Stop all running processes
-    pub fn stop_all(&self) {
-        let mut procs = self.processes.lock().unwrap();
-        for (project_id, info) in procs.iter_mut() {
-            if let Some(ref mut child) = info.child {
-                let pid = child.id();
-                
-                // On Windows, use taskkill to kill the entire process tree
-                #[cfg(windows)]
-                {
-                    let _ = std::process::Command::new("taskkill")
-                        .args(["/F", "/T", "/PID", &pid.to_string()])
-                        .creation_flags(0x08000000)
-                        .output();
-                }
-                
-                let _ = child.kill();
-                let _ = child.wait();
+    /// Stop all running processes, dependents before their dependencies
+    pub async fn stop_all(&self) {
+        // Service units keep their project's name and dependencies
+        let mut projects: Vec<Project> = Vec::new();
+        for info in self.processes.lock().unwrap().values() {
+            let (project_id, _) = config::split_unit_id(&info.project.id);
+            if !projects.iter().any(|p| p.id == project_id) {
+                let mut project = info.project.clone();
+                project.id = project_id.to_string();
+                projects.push(project);
             }
-            info.status = ProcessStatus::Stopped;
-            info.child = None;
-
-            self.emit_event("process-status", StatusPayload {
-                project_id: project_id.clone(),
-                status: "stopped".to_string(),
-            });
         }
+        let ids: Vec<&str> = projects.iter().map(|p| p.id.as_str()).collect();
 
-        // Clear all stdin handles
-        {
-            let mut stdin_handles = self.stdin_handles.lock().unwrap();
-            stdin_handles.clear();
+        for project_id in dependency::stop_order(&projects, &ids) {
+            let _ = self.stop(&project_id).await;
         }
     }
 }
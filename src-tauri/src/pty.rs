@@ -0,0 +1,262 @@
+//! Pseudo-terminal support
+//! Runs a project on the slave side of a PTY so tools see a real terminal
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::process::Command;
+
+use serde::Deserialize;
+
+/// A terminal control key delivered to a running project
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ControlKey {
+    /// Ctrl+C, SIGINT
+    Interrupt,
+    /// Ctrl+Z, SIGTSTP
+    Suspend,
+    /// Ctrl+D, end of input
+    Eof,
+}
+
+impl ControlKey {
+    /// The byte the terminal line discipline maps to this key by default
+    pub fn byte(self) -> u8 {
+        match self {
+            ControlKey::Interrupt => 0x03,
+            ControlKey::Suspend => 0x1a,
+            ControlKey::Eof => 0x04,
+        }
+    }
+
+    /// Caret notation used when echoing the key to the log
+    pub fn caret(self) -> &'static str {
+        match self {
+            ControlKey::Interrupt => "^C",
+            ControlKey::Suspend => "^Z",
+            ControlKey::Eof => "^D",
+        }
+    }
+}
+
+/// The master side of a pseudo-terminal.
+/// Writing sends keystrokes to the project; reading yields its raw output.
+#[derive(Debug)]
+pub struct PtyMaster {
+    file: File,
+}
+
+impl PtyMaster {
+    /// A non-blocking handle for reading output.
+    /// Must be called within the tokio runtime.
+    #[cfg(unix)]
+    pub fn reader(&self) -> io::Result<PtyReader> {
+        use std::os::fd::AsRawFd;
+
+        let file = self.file.try_clone()?;
+        // The flag is shared with the writer, which fails fast on a full buffer
+        unsafe {
+            let flags = libc::fcntl(file.as_raw_fd(), libc::F_GETFL);
+            libc::fcntl(file.as_raw_fd(), libc::F_SETFL, flags | libc::O_NONBLOCK);
+        }
+        Ok(PtyReader {
+            fd: tokio::io::unix::AsyncFd::new(file)?,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn reader(&self) -> io::Result<PtyReader> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "PTY mode is only supported on Unix"))
+    }
+
+    /// Change the terminal size; the foreground process gets `SIGWINCH`
+    #[cfg(unix)]
+    pub fn resize(&self, cols: u16, rows: u16) -> Result<(), String> {
+        use std::os::fd::AsRawFd;
+
+        let size = winsize(cols, rows);
+        let rc = unsafe { libc::ioctl(self.file.as_raw_fd(), libc::TIOCSWINSZ as _, &size) };
+        if rc == -1 {
+            return Err(format!("Failed to resize terminal: {}", io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub fn resize(&self, _cols: u16, _rows: u16) -> Result<(), String> {
+        Err("PTY mode is only supported on Unix".to_string())
+    }
+}
+
+impl Write for &PtyMaster {
+    /// Fails with `WouldBlock` rather than waiting while the terminal's input
+    /// buffer is full, since the reader made the master non-blocking
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (&self.file).write(buf).map_err(|e| match e.kind() {
+            io::ErrorKind::WouldBlock => io::Error::new(io::ErrorKind::WouldBlock, "input buffer full"),
+            _ => e,
+        })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (&self.file).flush()
+    }
+}
+
+/// Reads a project's raw terminal output through the tokio reactor
+#[derive(Debug)]
+pub struct PtyReader {
+    #[cfg(unix)]
+    fd: tokio::io::unix::AsyncFd<File>,
+}
+
+impl PtyReader {
+    /// Read the next chunk of output; `Ok(0)` or an error means the terminal closed
+    #[cfg(unix)]
+    pub async fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        use std::io::Read;
+
+        loop {
+            let mut guard = self.fd.readable().await?;
+            match guard.try_io(|fd| fd.get_ref().read(buf)) {
+                Ok(result) => return result,
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub async fn read(&self, _buf: &mut [u8]) -> io::Result<usize> {
+        Ok(0)
+    }
+}
+
+/// Open a new pseudo-terminal of the given size.
+/// Returns the master and the slave to hand to the child.
+#[cfg(unix)]
+pub fn open(cols: u16, rows: u16) -> Result<(PtyMaster, File), String> {
+    use std::os::fd::FromRawFd;
+
+    let mut master: libc::c_int = -1;
+    let mut slave: libc::c_int = -1;
+    let size = winsize(cols, rows);
+    // Some platforms declare the size argument as mutable
+    let rc = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::addr_of!(size).cast_mut(),
+        )
+    };
+    if rc == -1 {
+        return Err(format!("Failed to open pseudo-terminal: {}", io::Error::last_os_error()));
+    }
+
+    // Neither end should leak into other projects spawned later
+    unsafe {
+        libc::fcntl(master, libc::F_SETFD, libc::FD_CLOEXEC);
+        libc::fcntl(slave, libc::F_SETFD, libc::FD_CLOEXEC);
+    }
+
+    let master = unsafe { File::from_raw_fd(master) };
+    let slave = unsafe { File::from_raw_fd(slave) };
+    Ok((PtyMaster { file: master }, slave))
+}
+
+#[cfg(not(unix))]
+pub fn open(_cols: u16, _rows: u16) -> Result<(PtyMaster, File), String> {
+    Err("PTY mode is only supported on Unix".to_string())
+}
+
+/// Connect the command's stdio to the slave and make it the controlling
+/// terminal of a new session, so control keys become signals.
+///
+/// The session leader is also a process group leader, so the command must
+/// not be put in a group with `process_group::set_new_group` as well.
+#[cfg(unix)]
+pub fn attach(command: &mut Command, slave: &File) -> Result<(), String> {
+    use std::os::unix::process::CommandExt;
+    use std::process::Stdio;
+
+    let clone = || {
+        slave
+            .try_clone()
+            .map(Stdio::from)
+            .map_err(|e| format!("Failed to attach pseudo-terminal: {}", e))
+    };
+    command.stdin(clone()?).stdout(clone()?).stderr(clone()?);
+
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setsid() == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            // stdin is the slave by now
+            if libc::ioctl(0, libc::TIOCSCTTY as _, 0) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn attach(_command: &mut Command, _slave: &File) -> Result<(), String> {
+    Err("PTY mode is only supported on Unix".to_string())
+}
+
+#[cfg(unix)]
+fn winsize(cols: u16, rows: u16) -> libc::winsize {
+    libc::winsize {
+        ws_row: rows.max(1),
+        ws_col: cols.max(1),
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    }
+}
+
+/// Decodes a byte stream as UTF-8 without splitting multi-byte characters
+/// that straddle two reads
+#[derive(Debug, Default)]
+pub struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    /// Decode `bytes` after any incomplete tail from the previous call.
+    /// Invalid sequences are replaced with U+FFFD.
+    pub fn decode(&mut self, bytes: &[u8]) -> String {
+        self.pending.extend_from_slice(bytes);
+        let keep = match std::str::from_utf8(&self.pending) {
+            Ok(_) => 0,
+            // An incomplete sequence at the very end may finish in the next read
+            Err(e) if e.error_len().is_none() => self.pending.len() - e.valid_up_to(),
+            Err(_) => incomplete_tail(&self.pending),
+        };
+        let tail = self.pending.split_off(self.pending.len() - keep);
+        let text = String::from_utf8_lossy(&self.pending).into_owned();
+        self.pending = tail;
+        text
+    }
+}
+
+/// Length of a truncated multi-byte sequence at the end of `bytes`
+fn incomplete_tail(bytes: &[u8]) -> usize {
+    for len in 1..=3.min(bytes.len()) {
+        let start = bytes.len() - len;
+        let lead = bytes[start];
+        if lead & 0xC0 != 0x80 {
+            let needed = match lead {
+                0xC0..=0xDF => 2,
+                0xE0..=0xEF => 3,
+                0xF0..=0xF7 => 4,
+                _ => 1,
+            };
+            return if needed > len { len } else { 0 };
+        }
+    }
+    0
+}
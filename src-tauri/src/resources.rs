@@ -0,0 +1,121 @@
+//! Resource usage sampling for project process trees
+//! Reads /proc on Linux; other platforms report no usage
+
+use serde::Serialize;
+
+/// One resource usage sample of a project's whole process tree
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceSample {
+    /// RFC 3339 time the sample was taken
+    pub timestamp: String,
+    /// CPU usage since the previous sample; 100 is one full core
+    pub cpu_percent: f64,
+    pub rss_bytes: u64,
+    pub threads: u64,
+    /// Processes in the tree below the group leader
+    pub children: u64,
+}
+
+/// Raw totals over a process tree at one point in time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TreeUsage {
+    /// User plus system CPU time in clock ticks
+    pub cpu_ticks: u64,
+    pub rss_bytes: u64,
+    pub threads: u64,
+    /// Number of processes, including the root
+    pub processes: u64,
+}
+
+/// Clock ticks per second used by `TreeUsage::cpu_ticks`
+#[cfg(target_os = "linux")]
+pub fn ticks_per_second() -> u64 {
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks > 0 {
+        ticks as u64
+    } else {
+        100
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn ticks_per_second() -> u64 {
+    100
+}
+
+/// Sum the usage of `pid` and all of its descendants.
+/// Returns `None` if the root process no longer exists.
+#[cfg(target_os = "linux")]
+pub fn sample_tree(pid: u32) -> Option<TreeUsage> {
+    let page_size = match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        size if size > 0 => size as u64,
+        _ => 4096,
+    };
+
+    let root = pid;
+    let mut usage = TreeUsage::default();
+    let mut pending = vec![root];
+    let mut seen = std::collections::HashSet::new();
+    while let Some(pid) = pending.pop() {
+        if !seen.insert(pid) {
+            continue;
+        }
+        // Descendants may exit while the tree is being walked
+        let Some((cpu_ticks, threads)) = read_stat(pid) else {
+            if pid == root {
+                return None;
+            }
+            continue;
+        };
+        usage.cpu_ticks += cpu_ticks;
+        usage.threads += threads;
+        usage.rss_bytes += read_rss_pages(pid).unwrap_or(0) * page_size;
+        usage.processes += 1;
+        pending.extend(read_children(pid));
+    }
+    Some(usage)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn sample_tree(_pid: u32) -> Option<TreeUsage> {
+    None
+}
+
+/// utime + stime and the thread count from `/proc/<pid>/stat`
+#[cfg(target_os = "linux")]
+fn read_stat(pid: u32) -> Option<(u64, u64)> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // Fields after the parenthesised command name start at field 3 (state)
+    let (_, rest) = stat.rsplit_once(')')?;
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    let field = |n: usize| fields.get(n - 3).and_then(|f| f.parse::<u64>().ok());
+    let utime = field(14)?;
+    let stime = field(15)?;
+    let threads = field(20)?;
+    Some((utime + stime, threads))
+}
+
+/// Resident set size in pages from `/proc/<pid>/statm`
+#[cfg(target_os = "linux")]
+fn read_rss_pages(pid: u32) -> Option<u64> {
+    let statm = std::fs::read_to_string(format!("/proc/{}/statm", pid)).ok()?;
+    statm.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Direct children of every thread of `pid`
+#[cfg(target_os = "linux")]
+fn read_children(pid: u32) -> Vec<u32> {
+    let Ok(tasks) = std::fs::read_dir(format!("/proc/{}/task", pid)) else {
+        return Vec::new();
+    };
+    tasks
+        .flatten()
+        .filter_map(|task| std::fs::read_to_string(task.path().join("children")).ok())
+        .flat_map(|children| {
+            children
+                .split_whitespace()
+                .filter_map(|child| child.parse().ok())
+                .collect::<Vec<u32>>()
+        })
+        .collect()
+}
@@ -0,0 +1,226 @@
+//! Cron-driven project scheduling
+//! Starts, restarts and stops projects according to their `schedule`
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{Local, NaiveDateTime, Timelike};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{self, AppConfig, CatchUp, Project};
+use crate::cron::CronExpr;
+use crate::dependency;
+use crate::process_manager::{ProcessManager, ProcessStatus};
+
+const STATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+/// Persisted between app runs to find runs missed while DevBoot was closed
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SchedulerState {
+    /// Last minute the scheduler evaluated, in local time
+    last_tick: Option<String>,
+}
+
+fn state_path() -> std::path::PathBuf {
+    config::get_config_path().with_file_name("schedule_state.json")
+}
+
+fn load_last_tick() -> Option<NaiveDateTime> {
+    let content = std::fs::read_to_string(state_path()).ok()?;
+    let state: SchedulerState = serde_json::from_str(&content).ok()?;
+    NaiveDateTime::parse_from_str(&state.last_tick?, STATE_TIME_FORMAT).ok()
+}
+
+fn save_last_tick(time: NaiveDateTime) {
+    let state = SchedulerState {
+        last_tick: Some(time.format(STATE_TIME_FORMAT).to_string()),
+    };
+    if let Ok(content) = serde_json::to_string_pretty(&state) {
+        std::fs::write(state_path(), content).ok();
+    }
+}
+
+/// The current local time, truncated to the minute
+fn current_minute() -> NaiveDateTime {
+    let now = Local::now().naive_local();
+    now.with_second(0).and_then(|t| t.with_nanosecond(0)).unwrap_or(now)
+}
+
+fn parse(expr: &Option<String>) -> Option<(&str, CronExpr)> {
+    let expr = expr.as_deref()?;
+    CronExpr::parse(expr).ok().map(|parsed| (expr, parsed))
+}
+
+/// ` for HH:MM` when a fire is handled after its minute, because the tick
+/// before took longer than a minute
+fn late(fire: NaiveDateTime, now: NaiveDateTime) -> String {
+    if fire < now {
+        format!(" for {}", fire.format("%H:%M"))
+    } else {
+        String::new()
+    }
+}
+
+/// Whether a project may run now according to its `active_during` window
+pub fn in_active_window(project: &Project) -> bool {
+    match project.schedule.as_ref().and_then(|s| parse(&s.active_during)) {
+        Some((_, window)) => window.matches(&current_minute()),
+        None => true,
+    }
+}
+
+/// Start the scheduler on the async runtime.
+///
+/// At startup it stops projects outside their active window and catches up
+/// on missed runs; after that it evaluates every schedule once a minute,
+/// including minutes that passed while a slow tick was stopping projects.
+pub fn start(config: Arc<Mutex<AppConfig>>, manager: ProcessManager) {
+    tauri::async_runtime::spawn(async move {
+        let mut scheduler = Scheduler {
+            config,
+            manager,
+            inside_window: HashMap::new(),
+        };
+
+        let mut last = current_minute();
+        scheduler.startup(last, load_last_tick()).await;
+        save_last_tick(last);
+
+        loop {
+            let now = Local::now();
+            let into_minute = Duration::from_secs(now.second() as u64)
+                + Duration::from_nanos(now.nanosecond().min(999_999_999) as u64);
+            tokio::time::sleep(Duration::from_secs(60).saturating_sub(into_minute)).await;
+
+            let minute = current_minute();
+            // Woke early, or the clock was turned back
+            if minute <= last {
+                continue;
+            }
+            scheduler.tick(last, minute).await;
+            save_last_tick(minute);
+            last = minute;
+        }
+    });
+}
+
+struct Scheduler {
+    config: Arc<Mutex<AppConfig>>,
+    manager: ProcessManager,
+    /// Whether each windowed project was inside its window at the last tick
+    inside_window: HashMap<String, bool>,
+}
+
+impl Scheduler {
+    fn projects(&self) -> Vec<Project> {
+        self.config.lock().unwrap().projects.clone()
+    }
+
+    async fn startup(&mut self, now: NaiveDateTime, last_tick: Option<NaiveDateTime>) {
+        let projects = self.projects();
+        for project in projects.iter().filter(|p| p.enabled) {
+            let Some(schedule) = &project.schedule else {
+                continue;
+            };
+
+            if let Some((expr, window)) = parse(&schedule.active_during) {
+                let inside = window.matches(&now);
+                self.inside_window.insert(project.id.clone(), inside);
+                if !inside && self.manager.is_running(&project.id) {
+                    self.log(project, format!("Outside active window '{}', stopping", expr));
+                    self.stop(&projects, &project.id).await;
+                }
+            }
+
+            let (Some(last_tick), CatchUp::RunOnce) = (last_tick, schedule.catch_up) else {
+                continue;
+            };
+            if let Some((expr, run)) = parse(&schedule.run) {
+                if let Some(missed) = run.last_fire(last_tick, now) {
+                    let trigger = format!(
+                        "Catch-up run for {} missed while closed (cron '{}')",
+                        missed.format("%Y-%m-%d %H:%M"),
+                        expr
+                    );
+                    self.run(&projects, project, trigger);
+                }
+            }
+        }
+    }
+
+    /// Apply windows as of `now` and fire what was due in `(last, now]`
+    async fn tick(&mut self, last: NaiveDateTime, now: NaiveDateTime) {
+        let projects = self.projects();
+        for project in projects.iter().filter(|p| p.enabled) {
+            let Some(schedule) = &project.schedule else {
+                continue;
+            };
+
+            let mut allowed = true;
+            if let Some((expr, window)) = parse(&schedule.active_during) {
+                allowed = window.matches(&now);
+                let was_inside = self.inside_window.insert(project.id.clone(), allowed);
+                let running = self.manager.is_running(&project.id);
+                if allowed && was_inside == Some(false) && !running {
+                    self.run(&projects, project, format!("Active window '{}' opened", expr));
+                } else if !allowed && running {
+                    self.log(project, format!("Outside active window '{}', stopping", expr));
+                    self.stop(&projects, &project.id).await;
+                }
+            }
+
+            if let Some((expr, restart)) = parse(&schedule.restart) {
+                let due = restart.last_fire(last, now);
+                if let Some(fire) = due.filter(|_| allowed && self.manager.is_running(&project.id)) {
+                    self.log(project, format!("Scheduled restart{} (cron '{}')", late(fire, now), expr));
+                    // Only this project restarts; its dependents keep running
+                    let _ = self.manager.stop(&project.id).await;
+                    self.start(&projects, project);
+                }
+            }
+
+            if let Some((expr, run)) = parse(&schedule.run) {
+                let Some(fire) = run.last_fire(last, now) else {
+                    continue;
+                };
+                let trigger = format!("Scheduled run{} (cron '{}')", late(fire, now), expr);
+                if allowed {
+                    self.run(&projects, project, trigger);
+                } else {
+                    self.log(project, format!("{} skipped: outside active window", trigger));
+                }
+            }
+        }
+    }
+
+    /// Start a project with its dependencies unless it is already running
+    fn run(&self, projects: &[Project], project: &Project, trigger: String) {
+        if self.manager.is_running(&project.id) {
+            self.log(project, format!("{} skipped: already running", trigger));
+            return;
+        }
+        self.log(project, trigger);
+        self.start(projects, project);
+    }
+
+    fn start(&self, projects: &[Project], project: &Project) {
+        match dependency::start_order(projects, &[project.id.as_str()]) {
+            Ok(plan) => self.manager.start_in_order(plan),
+            Err(e) => self.manager.add_system_error(project, format!("Failed to start: {}", e)),
+        }
+    }
+
+    /// Stop a project after the projects that depend on it
+    async fn stop(&self, projects: &[Project], project_id: &str) {
+        for id in dependency::stop_order(projects, &[project_id]) {
+            if id == project_id || self.manager.get_status(&id) != ProcessStatus::Stopped {
+                let _ = self.manager.stop(&id).await;
+            }
+        }
+    }
+
+    fn log(&self, project: &Project, message: String) {
+        self.manager.add_system_log(project, message);
+    }
+}
@@ -0,0 +1,248 @@
+//! Shell backends for spawning project commands
+//! Each profile knows its executable, how to pass a script and how to quote
+
+use std::process::Command;
+
+use crate::config::ShellProfile;
+
+/// A resolved program and argument list ready to spawn
+#[derive(Debug, Clone)]
+pub struct ShellInvocation {
+    pub program: String,
+    pub args: Vec<String>,
+    profile: ShellProfile,
+}
+
+impl ShellInvocation {
+    /// Build the invocation that runs `commands` with the given profile.
+    ///
+    /// Shell profiles chain the commands with `&&`. `Direct` runs a single
+    /// command without any shell, splitting it into argv with POSIX-style
+    /// quoting rules.
+    pub fn new(profile: ShellProfile, commands: &[String]) -> Result<Self, String> {
+        if commands.is_empty() {
+            return Err("No commands to run".to_string());
+        }
+
+        if profile == ShellProfile::Direct {
+            if commands.len() != 1 {
+                return Err("Direct exec runs exactly one command; use a shell profile to chain commands".to_string());
+            }
+            let mut argv = split_args(&commands[0])?;
+            if argv.is_empty() {
+                return Err("Direct exec command is empty".to_string());
+            }
+            let program = argv.remove(0);
+            return Ok(Self { program, args: argv, profile });
+        }
+
+        let script = commands.join(" && ");
+        let args = match profile {
+            ShellProfile::Pwsh => vec![
+                "-NoLogo".to_string(),
+                "-NoProfile".to_string(),
+                "-Command".to_string(),
+                script,
+            ],
+            _ => vec!["-c".to_string(), script],
+        };
+
+        Ok(Self {
+            program: resolve_program(profile),
+            args,
+            profile,
+        })
+    }
+
+    /// Create a `Command` for this invocation
+    pub fn to_command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args);
+        command
+    }
+
+    /// Human-readable command line, quoted for the profile's shell
+    pub fn display(&self) -> String {
+        std::iter::once(&self.program)
+            .chain(self.args.iter())
+            .map(|arg| quote(self.profile, arg))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Find the executable for a shell profile
+fn resolve_program(profile: ShellProfile) -> String {
+    match profile {
+        ShellProfile::Bash => find_bash(),
+        ShellProfile::Sh => "sh".to_string(),
+        ShellProfile::Zsh => "zsh".to_string(),
+        ShellProfile::Fish => "fish".to_string(),
+        ShellProfile::Pwsh => "pwsh".to_string(),
+        ShellProfile::Direct => String::new(),
+    }
+}
+
+/// Find Git Bash on Windows, falling back to `bash` from PATH
+fn find_bash() -> String {
+    #[cfg(windows)]
+    {
+        let possible_paths = [
+            "C:\\Program Files\\Git\\bin\\bash.exe",
+            "C:\\Program Files (x86)\\Git\\bin\\bash.exe",
+            "C:\\Git\\bin\\bash.exe",
+        ];
+
+        for path in possible_paths {
+            if std::path::Path::new(path).exists() {
+                return path.to_string();
+            }
+        }
+    }
+
+    "bash".to_string()
+}
+
+/// Quote a single word so the profile's shell reads it back unchanged
+pub fn quote(profile: ShellProfile, word: &str) -> String {
+    let is_plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c));
+    if is_plain {
+        return word.to_string();
+    }
+
+    match profile {
+        // POSIX shells: close the quote, emit an escaped quote, reopen
+        ShellProfile::Bash | ShellProfile::Sh | ShellProfile::Zsh | ShellProfile::Direct => {
+            format!("'{}'", word.replace('\'', "'\\''"))
+        }
+        // fish allows backslash escapes for \ and ' inside single quotes
+        ShellProfile::Fish => {
+            format!("'{}'", word.replace('\\', "\\\\").replace('\'', "\\'"))
+        }
+        // PowerShell doubles single quotes inside a single-quoted string
+        ShellProfile::Pwsh => format!("'{}'", word.replace('\'', "''")),
+    }
+}
+
+/// Split a command line into argv using POSIX-style quoting.
+///
+/// Supports single quotes (literal), double quotes (with `\"`, `\\` and `\$`
+/// escapes) and, except on Windows, backslash escapes outside quotes.
+pub fn split_args(line: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(ch) => current.push(ch),
+                        None => return Err("Unterminated single quote in command".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(ch @ ('"' | '\\' | '$' | '`')) => current.push(ch),
+                            Some(ch) => {
+                                current.push('\\');
+                                current.push(ch);
+                            }
+                            None => return Err("Unterminated double quote in command".to_string()),
+                        },
+                        Some(ch) => current.push(ch),
+                        None => return Err("Unterminated double quote in command".to_string()),
+                    }
+                }
+            }
+            // Windows paths use backslashes, so only treat them as escapes elsewhere
+            '\\' if cfg!(not(windows)) => {
+                in_word = true;
+                match chars.next() {
+                    Some(ch) => current.push(ch),
+                    None => return Err("Trailing backslash in command".to_string()),
+                }
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    args.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            _ => {
+                in_word = true;
+                current.push(c);
+            }
+        }
+    }
+
+    if in_word {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(line: &str) -> Vec<String> {
+        split_args(line).unwrap()
+    }
+
+    #[test]
+    fn splits_on_any_whitespace() {
+        assert_eq!(split("  npm   run\tdev \n"), ["npm", "run", "dev"]);
+        assert!(split("   ").is_empty());
+    }
+
+    #[test]
+    fn single_quotes_are_literal() {
+        assert_eq!(split(r#"echo 'a "b" \n $HOME'"#), ["echo", r#"a "b" \n $HOME"#]);
+    }
+
+    #[test]
+    fn double_quotes_keep_only_known_escapes() {
+        assert_eq!(split(r#"echo "say \"hi\" \\ \$x \n""#), ["echo", r#"say "hi" \ $x \n"#]);
+    }
+
+    #[test]
+    fn quoted_parts_join_one_word() {
+        assert_eq!(split(r#"--name='my app'"suffix" '' """#), ["--name=my appsuffix", "", ""]);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn backslash_escapes_outside_quotes() {
+        assert_eq!(split(r"touch my\ file \'q"), ["touch", "my file", "'q"]);
+        assert_eq!(split_args(r"echo \").unwrap_err(), "Trailing backslash in command");
+    }
+
+    #[test]
+    fn rejects_unterminated_quotes() {
+        assert_eq!(split_args("echo 'open").unwrap_err(), "Unterminated single quote in command");
+        assert_eq!(split_args(r#"echo "open"#).unwrap_err(), "Unterminated double quote in command");
+        assert_eq!(split_args(r#"echo "open\"#).unwrap_err(), "Unterminated double quote in command");
+    }
+
+    // An embedded single quote is escaped with a backslash outside quotes
+    #[cfg(not(windows))]
+    #[test]
+    fn posix_quoting_round_trips() {
+        for word in ["plain", "two words", "it's", "", r#"a "b" \c $d"#] {
+            assert_eq!(split(&quote(ShellProfile::Bash, word)), [word]);
+        }
+    }
+}
@@ -0,0 +1,360 @@
+//! File watching for projects with a `watch` config
+//! Restarts a project when files matching its globs change
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use notify::event::ModifyKind;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher, WatcherKind};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::time::Instant;
+
+use crate::config::{AppConfig, Project, WatchConfig};
+use crate::process_manager::{ProcessManager, ProcessStatus};
+
+/// How often watches are brought in line with the current config
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A change reported for one project
+type ChangeEvent = (String, notify::Event);
+
+/// Include and exclude globs of a `WatchConfig`, matched against paths
+/// relative to the project path with `/` separators
+pub struct Matcher {
+    /// `None` matches every file
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+impl Matcher {
+    pub fn new(watch: &WatchConfig) -> Result<Self, String> {
+        let include = if watch.include.is_empty() {
+            None
+        } else {
+            Some(build_set(&watch.include)?)
+        };
+        Ok(Self {
+            include,
+            exclude: build_set(&watch.exclude)?,
+        })
+    }
+
+    pub fn is_match(&self, relative: &str) -> bool {
+        !self.exclude.is_match(relative)
+            && self.include.as_ref().is_none_or(|include| include.is_match(relative))
+    }
+
+    /// Whether everything under a directory is excluded, as with
+    /// `**/node_modules/**`
+    pub fn excludes_dir(&self, relative: &str) -> bool {
+        self.exclude.is_match(format!("{}/", relative))
+    }
+}
+
+fn build_set(patterns: &[String]) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).map_err(|e| e.to_string())?);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// Start watching on the async runtime.
+///
+/// Watches follow config edits within a few seconds. Changes to a project
+/// that is not stopped restart it once no further change arrived for
+/// `debounce_ms`.
+pub fn start(config: Arc<Mutex<AppConfig>>, manager: ProcessManager) {
+    tauri::async_runtime::spawn(async move {
+        let (events, mut changes) = mpsc::unbounded_channel();
+        let mut watcher = ProjectWatcher {
+            config,
+            manager,
+            events,
+            watches: HashMap::new(),
+            failed: HashMap::new(),
+            pending: HashMap::new(),
+        };
+        watcher.run(&mut changes).await;
+    });
+}
+
+/// An active watch on one project's directory
+struct Watch {
+    path: String,
+    config: WatchConfig,
+    root: PathBuf,
+    /// Some platforms report canonical paths regardless of the watched path
+    canonical_root: Option<PathBuf>,
+    matcher: Matcher,
+    watcher: RecommendedWatcher,
+}
+
+impl Watch {
+    fn new(project: &Project, config: &WatchConfig, events: UnboundedSender<ChangeEvent>) -> Result<Self, String> {
+        let matcher = Matcher::new(config)?;
+        let root = PathBuf::from(&project.path);
+        let project_id = project.id.clone();
+        let watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+            if let Ok(event) = result {
+                if !matches!(event.kind, EventKind::Access(_)) {
+                    let _ = events.send((project_id.clone(), event));
+                }
+            }
+        })
+        .map_err(|e| format!("Failed to watch files: {}", e))?;
+
+        let mut watch = Self {
+            path: project.path.clone(),
+            config: config.clone(),
+            canonical_root: root.canonicalize().ok(),
+            root: root.clone(),
+            matcher,
+            watcher,
+        };
+        let watched = if Self::per_directory() {
+            watch.watch_tree(&root)
+        } else {
+            watch.watcher.watch(&root, RecursiveMode::Recursive)
+        };
+        watched.map_err(|e| format!("Failed to watch '{}': {}", project.path, e))?;
+        Ok(watch)
+    }
+
+    /// Whether each directory needs a watch of its own, as with inotify,
+    /// where a recursive watch would also cover excluded directories and
+    /// use up the user's watch limit. Other backends watch a whole tree
+    /// through one handle.
+    fn per_directory() -> bool {
+        RecommendedWatcher::kind() == WatcherKind::Inotify
+    }
+
+    /// Watch `dir` and every directory below it that is not excluded.
+    /// Symlinks are not followed.
+    fn watch_tree(&mut self, dir: &Path) -> notify::Result<()> {
+        let mut pending = vec![dir.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            match self.watcher.watch(&dir, RecursiveMode::NonRecursive) {
+                Ok(()) => {}
+                // Removed again meanwhile
+                Err(e) if dir != self.root && is_not_found(&e) => continue,
+                Err(e) => return Err(e),
+            }
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                if !entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                    continue;
+                }
+                let path = entry.path();
+                if self.relative(&path).is_some_and(|relative| self.matcher.excludes_dir(&relative)) {
+                    continue;
+                }
+                pending.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    /// Watch a directory created or moved into the tree, with its contents
+    fn add_directory(&mut self, event: &notify::Event) -> notify::Result<()> {
+        if !Self::per_directory()
+            || !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_)))
+        {
+            return Ok(());
+        }
+        for path in &event.paths {
+            let Some(relative) = self.relative(path) else {
+                continue;
+            };
+            if path.is_dir() && !path.is_symlink() && !self.matcher.excludes_dir(&relative) {
+                self.watch_tree(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// `path` relative to the project path, or `None` if it lies outside
+    fn relative(&self, path: &Path) -> Option<String> {
+        let relative = path
+            .strip_prefix(&self.root)
+            .ok()
+            .or_else(|| path.strip_prefix(self.canonical_root.as_ref()?).ok())?;
+        let parts: Vec<_> = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+        if parts.is_empty() {
+            return None;
+        }
+        Some(parts.join("/"))
+    }
+}
+
+fn is_not_found(error: &notify::Error) -> bool {
+    match &error.kind {
+        notify::ErrorKind::PathNotFound => true,
+        notify::ErrorKind::Io(e) => e.kind() == std::io::ErrorKind::NotFound,
+        _ => false,
+    }
+}
+
+/// A restart waiting for its debounce interval to pass
+struct PendingRestart {
+    deadline: Instant,
+    /// The first file that changed
+    trigger: String,
+    /// Other files that changed since
+    others: HashSet<String>,
+}
+
+struct ProjectWatcher {
+    config: Arc<Mutex<AppConfig>>,
+    manager: ProcessManager,
+    events: UnboundedSender<ChangeEvent>,
+    watches: HashMap<String, Watch>,
+    /// Path and config of watches that could not be set up, logged once each
+    failed: HashMap<String, (String, WatchConfig)>,
+    pending: HashMap<String, PendingRestart>,
+}
+
+impl ProjectWatcher {
+    async fn run(&mut self, changes: &mut UnboundedReceiver<ChangeEvent>) {
+        let mut reconcile = tokio::time::interval(RECONCILE_INTERVAL);
+        loop {
+            let next_deadline = self.pending.values().map(|p| p.deadline).min();
+            tokio::select! {
+                _ = reconcile.tick() => self.reconcile(),
+                Some((project_id, event)) = changes.recv() => self.record(project_id, event),
+                _ = tokio::time::sleep_until(next_deadline.unwrap_or_else(Instant::now)), if next_deadline.is_some() => {
+                    self.restart_due().await;
+                }
+            }
+        }
+    }
+
+    /// Add, replace and drop watches to match the current config
+    fn reconcile(&mut self) {
+        let projects = self.config.lock().unwrap().projects.clone();
+        let wanted: HashMap<&str, (&Project, &WatchConfig)> = projects
+            .iter()
+            .filter(|p| p.enabled)
+            .filter_map(|p| Some((p.id.as_str(), (p, p.watch.as_ref()?))))
+            .collect();
+
+        self.watches.retain(|id, watch| {
+            wanted
+                .get(id.as_str())
+                .is_some_and(|(project, config)| watch.path == project.path && watch.config == **config)
+        });
+        self.failed.retain(|id, _| wanted.contains_key(id.as_str()));
+        let watches = &self.watches;
+        self.pending.retain(|id, _| watches.contains_key(id));
+
+        for (id, (project, config)) in wanted {
+            if self.watches.contains_key(id) {
+                continue;
+            }
+            match Watch::new(project, config, self.events.clone()) {
+                Ok(watch) => {
+                    self.failed.remove(id);
+                    self.watches.insert(id.to_string(), watch);
+                }
+                Err(e) => {
+                    let key = (project.path.clone(), config.clone());
+                    if self.failed.get(id) != Some(&key) {
+                        self.manager.add_system_error(project, e);
+                        self.failed.insert(id.to_string(), key);
+                    }
+                }
+            }
+        }
+    }
+
+    fn record(&mut self, project_id: String, event: notify::Event) {
+        let Some(watch) = self.watches.get_mut(&project_id) else {
+            return;
+        };
+        if let Err(e) = watch.add_directory(&event) {
+            let project = self.config.lock().unwrap().projects.iter().find(|p| p.id == project_id).cloned();
+            if let Some(project) = project {
+                self.manager
+                    .add_system_error(&project, format!("Failed to watch new directory: {}", e));
+            }
+        }
+        // A project the user stopped stays stopped
+        if self.manager.get_status(&project_id) == ProcessStatus::Stopped {
+            return;
+        }
+
+        let deadline = Instant::now() + Duration::from_millis(watch.config.debounce_ms);
+        for path in event.paths {
+            let Some(relative) = watch.relative(&path) else {
+                continue;
+            };
+            if !watch.matcher.is_match(&relative) {
+                continue;
+            }
+            match self.pending.get_mut(&project_id) {
+                Some(pending) => {
+                    pending.deadline = deadline;
+                    if pending.trigger != relative {
+                        pending.others.insert(relative);
+                    }
+                }
+                None => {
+                    self.pending.insert(
+                        project_id.clone(),
+                        PendingRestart {
+                            deadline,
+                            trigger: relative,
+                            others: HashSet::new(),
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    async fn restart_due(&mut self) {
+        let now = Instant::now();
+        let due: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| pending.deadline <= now)
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for project_id in due {
+            let Some(pending) = self.pending.remove(&project_id) else {
+                continue;
+            };
+            let project = self
+                .config
+                .lock()
+                .unwrap()
+                .projects
+                .iter()
+                .find(|p| p.id == project_id)
+                .cloned();
+            let Some(project) = project else {
+                continue;
+            };
+            if self.manager.get_status(&project_id) == ProcessStatus::Stopped {
+                continue;
+            }
+
+            let message = match pending.others.len() {
+                0 => format!("File changed: {}, restarting", pending.trigger),
+                n => format!("File changed: {} (and {} more), restarting", pending.trigger, n),
+            };
+            self.manager.add_system_log(&project, message);
+            // Only this project restarts; its dependents keep running
+            let _ = self.manager.stop(&project_id).await;
+            if let Err(e) = self.manager.start_project(&project) {
+                self.manager.add_system_error(&project, format!("Failed to restart: {}", e));
+            }
+        }
+    }
+}
@@ -7,7 +7,7 @@ import { ConfirmDialog } from './components/ConfirmDialog';
 import { ToastProvider, useToast } from './components/Toast';
 import { ErrorBoundary } from './components/ErrorBoundary';
 import { useProjects, useSettings } from './hooks/useProjects';
-import { Project } from './types';
+import { Project, isActiveStatus } from './types';
 import './App.css';
 
 function AppContent() {
@@ -23,6 +23,9 @@ function AppContent() {
     stopProject,
     restartProject,
     clearLogs,
+    olderLogs,
+    loadOlderLogs,
+    searchLogs,
   } = useProjects();
 
   const { settings, updateSettings } = useSettings();
@@ -64,13 +67,13 @@ function AppContent() {
             break;
           case 'enter':
             e.preventDefault();
-            if (selectedProject && statuses[selectedProject.id] !== 'running') {
+            if (selectedProject && !isActiveStatus(statuses[selectedProject.id])) {
               handleStartProject(selectedProject.id);
             }
             break;
           case '.':
             e.preventDefault();
-            if (selectedProject && statuses[selectedProject.id] === 'running') {
+            if (selectedProject && isActiveStatus(statuses[selectedProject.id])) {
               handleStopProject(selectedProject.id);
             }
             break;
@@ -223,10 +226,12 @@ function AppContent() {
               clearLogs(selectedProject.id);
               toast.info('Logs cleared');
             }}
+            onLoadOlder={olderLogs[selectedProject.id] != null ? () => loadOlderLogs(selectedProject.id) : undefined}
             onRestart={() => handleRestartProject(selectedProject.id)}
             onStop={() => handleStopProject(selectedProject.id)}
             onStart={() => handleStartProject(selectedProject.id)}
-            isRunning={statuses[selectedProject.id] === 'running'}
+            onSearch={pattern => searchLogs({ pattern, project_ids: [selectedProject.id] })}
+            isRunning={isActiveStatus(statuses[selectedProject.id])}
           />
         ) : (
           <div className="empty-state">
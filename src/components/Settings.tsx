@@ -1,5 +1,5 @@
 import { useState } from 'react';
-import { Settings as SettingsType } from '../types';
+import { ApiSettings, Settings as SettingsType, ShellProfile } from '../types';
 import './Settings.css';
 
 interface SettingsProps {
@@ -8,6 +8,8 @@ interface SettingsProps {
     onClose: () => void;
 }
 
+const SHELL_PROFILES: ShellProfile[] = ['bash', 'sh', 'zsh', 'fish', 'pwsh', 'direct'];
+
 const WALLET_ADDRESS = '0x051BF9b67aC43BbB461A33E13c21218f304E31BB';
 
 export function Settings({ settings, onSave, onClose }: SettingsProps) {
@@ -21,6 +23,11 @@ export function Settings({ settings, onSave, onClose }: SettingsProps) {
         }));
     };
 
+    const api = localSettings.api ?? { enabled: false, port: 7420 };
+    const setApi = (change: Partial<ApiSettings>) => {
+        setLocalSettings(prev => ({ ...prev, api: { ...api, ...change } }));
+    };
+
     const handleSave = () => {
         onSave(localSettings);
         onClose();
@@ -108,6 +115,47 @@ export function Settings({ settings, onSave, onClose }: SettingsProps) {
                         </div>
                     </div>
 
+                    <div className="settings-section">
+                        <h3>Default Shell</h3>
+                        <div className="theme-selector">
+                            {SHELL_PROFILES.map(shell => (
+                                <button
+                                    key={shell}
+                                    className={`theme-btn ${localSettings.default_shell === shell ? 'active' : ''}`}
+                                    onClick={() => setLocalSettings(prev => ({ ...prev, default_shell: shell }))}
+                                >
+                                    {shell}
+                                </button>
+                            ))}
+                        </div>
+                    </div>
+
+                    <div className="settings-section">
+                        <h3>Local API</h3>
+                        <label className="toggle-item">
+                            <span className="toggle-label">
+                                <span className="toggle-icon">{"[~]"}</span>
+                                Enable HTTP API (next launch)
+                            </span>
+                            <div
+                                className={`toggle ${api.enabled ? 'active' : ''}`}
+                                onClick={() => setApi({ enabled: !api.enabled })}
+                            >
+                                <div className="toggle-knob" />
+                            </div>
+                        </label>
+                        <label className="toggle-item">
+                            <span className="toggle-label">Port</span>
+                            <input
+                                type="number"
+                                min={1}
+                                max={65535}
+                                value={api.port}
+                                onChange={e => setApi({ port: Number(e.target.value) })}
+                            />
+                        </label>
+                    </div>
+
                     <div className="settings-section support-section">
                         <h3>Support & About</h3>
                         <div className="about-info">
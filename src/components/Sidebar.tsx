@@ -1,5 +1,5 @@
 import { useState } from 'react';
-import { Project, ProcessStatus } from '../types';
+import { Project, ProcessStatus, isActiveStatus } from '../types';
 import './Sidebar.css';
 
 interface SidebarProps {
@@ -31,8 +31,13 @@ export function Sidebar({
 
     const getStatusColor = (status: ProcessStatus) => {
         switch (status) {
-            case 'running': return 'var(--success)';
-            case 'error': return 'var(--error)';
+            case 'running':
+            case 'healthy': return 'var(--success)';
+            case 'error':
+            case 'backoff': return 'var(--error)';
+            case 'setup':
+            case 'starting':
+            case 'unhealthy':
             case 'restarting': return 'var(--warning)';
             default: return 'var(--text-muted)';
         }
@@ -41,8 +46,14 @@ export function Sidebar({
     const getStatusIcon = (status: ProcessStatus) => {
         switch (status) {
             case 'running': return '▶';
+            case 'healthy': return '✔';
+            case 'setup':
+            case 'starting':
+            case 'cleanup': return '…';
+            case 'unhealthy':
             case 'error': return '⚠';
             case 'restarting': return '↻';
+            case 'backoff': return '⏸';
             default: return '■';
         }
     };
@@ -94,7 +105,7 @@ export function Sidebar({
                     {filteredProjects.map((project) => {
                         const status = statuses[project.id] || 'stopped';
                         const isSelected = selectedId === project.id;
-                        const isRunning = status === 'running';
+                        const isRunning = isActiveStatus(status);
 
                         return (
                             <div
@@ -1,5 +1,6 @@
 import { useState, useEffect, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { LogSearch, formatLogEntry } from '../types';
 import './Terminal.css';
 
 interface TerminalProps {
@@ -7,25 +8,55 @@ interface TerminalProps {
     projectName: string;
     logs: string[];
     onClear: () => void;
+    // Set while older lines can be loaded
+    onLoadOlder?: () => void;
     onRestart: () => void;
     onStop: () => void;
     onStart: () => void;
+    // Search this project's logs, in memory and on disk
+    onSearch: (pattern: string) => Promise<LogSearch>;
     isRunning: boolean;
 }
 
+// Matches with their context, "--" between groups, as grep prints them
+const formatSearch = (result: LogSearch) => {
+    if (result.matches.length === 0) return ['No matching lines'];
+    const lines: string[] = [];
+    result.matches.forEach((match, index) => {
+        const label = (line: string) => match.service ? `[${match.service}] ${line}` : line;
+        if (index > 0 && (match.before.length > 0 || match.after.length > 0)) lines.push('--');
+        lines.push(...match.before.map(entry => label(formatLogEntry(entry))));
+        lines.push(label(formatLogEntry(match.entry)));
+        lines.push(...match.after.map(entry => label(formatLogEntry(entry))));
+    });
+    if (result.truncated) lines.push(`Only the newest ${result.matches.length} matches are shown`);
+    return lines;
+};
+
 export function Terminal({
     projectId,
     projectName,
     logs,
     onClear,
+    onLoadOlder,
     onRestart,
     onStop,
     onStart,
+    onSearch,
     isRunning,
 }: TerminalProps) {
     const logsEndRef = useRef<HTMLDivElement>(null);
     const inputRef = useRef<HTMLInputElement>(null);
     const [inputValue, setInputValue] = useState('');
+    const [searchValue, setSearchValue] = useState('');
+    // Shown instead of the live log while set
+    const [searchResults, setSearchResults] = useState<string[] | null>(null);
+
+    // A search belongs to the project it was made in
+    useEffect(() => {
+        setSearchValue('');
+        setSearchResults(null);
+    }, [projectId]);
 
     // Auto-scroll to bottom when new logs arrive
     useEffect(() => {
@@ -34,7 +65,7 @@ export function Terminal({
 
     const formatLog = (log: string) => {
         // Highlight errors in red
-        if (log.includes('[ERR]') || log.toLowerCase().includes('error')) {
+        if (log.toLowerCase().includes('error')) {
             return <span className="log-error">{log}</span>;
         }
         // Highlight warnings in yellow
@@ -74,6 +105,28 @@ export function Terminal({
         URL.revokeObjectURL(url);
     };
 
+    const handleSearch = async () => {
+        if (!searchValue.trim()) {
+            setSearchResults(null);
+            return;
+        }
+        try {
+            setSearchResults(formatSearch(await onSearch(searchValue)));
+        } catch (error) {
+            setSearchResults([`error: Search failed: ${error}`]);
+        }
+    };
+
+    const handleSearchKeyDown = (e: React.KeyboardEvent<HTMLInputElement>) => {
+        if (e.key === 'Enter') {
+            e.preventDefault();
+            handleSearch();
+        } else if (e.key === 'Escape') {
+            setSearchValue('');
+            setSearchResults(null);
+        }
+    };
+
     // Handle sending input to the process
     const handleSendInput = async () => {
         if (!inputValue.trim() || !isRunning) return;
@@ -127,6 +180,15 @@ export function Terminal({
                     </span>
                 </div>
                 <div className="terminal-actions">
+                    <input
+                        type="search"
+                        className="terminal-search"
+                        value={searchValue}
+                        onChange={(e) => setSearchValue(e.target.value)}
+                        onKeyDown={handleSearchKeyDown}
+                        placeholder="Search logs..."
+                        title="Search this project's logs, including past sessions (Enter; Esc to close)"
+                    />
                     {isRunning ? (
                         <button className="term-btn stop" onClick={onStop} title="Stop (Ctrl+.)">
                             ■ Stop
@@ -154,7 +216,18 @@ export function Terminal({
             </div>
 
             <div className="terminal-body">
-                {logs.length === 0 ? (
+                {searchResults ? (
+                    <div className="log-container">
+                        <button className="term-btn load-older" onClick={() => setSearchResults(null)}>
+                            Back to live output
+                        </button>
+                        {searchResults.map((line, index) => (
+                            <div key={index} className="log-line">
+                                {formatLog(line)}
+                            </div>
+                        ))}
+                    </div>
+                ) : logs.length === 0 ? (
                     <div className="terminal-empty">
                         <span className="empty-icon">📭</span>
                         <p>No logs yet</p>
@@ -162,6 +235,11 @@ export function Terminal({
                     </div>
                 ) : (
                     <div className="log-container">
+                        {onLoadOlder && (
+                            <button className="term-btn load-older" onClick={onLoadOlder}>
+                                Load older lines
+                            </button>
+                        )}
                         {logs.map((log, index) => (
                             <div key={index} className="log-line">
                                 {formatLog(log)}
@@ -2,6 +2,8 @@
 
 // Process Management
 export const MAX_LOG_LINES = 1000;
+// Lines fetched per page of a project's log
+export const LOG_PAGE_LINES = 200;
 export const POLLING_INTERVAL_MS = 1000;
 export const RESTART_DELAY_MS = 2000;
 export const MAX_RESTART_ATTEMPTS = 5;
@@ -28,10 +30,16 @@ export const SHORTCUTS = {
 
 // Status colors
 export const STATUS_COLORS = {
+  setup: '#3b82f6',
+  starting: '#3b82f6',
   running: '#10b981',
+  healthy: '#10b981',
+  unhealthy: '#f59e0b',
   error: '#ef4444',
   restarting: '#f59e0b',
+  backoff: '#ef4444',
   stopped: '#6b7280',
+  cleanup: '#6b7280',
 } as const;
 
 // File extensions for project detection
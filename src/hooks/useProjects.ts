@@ -1,23 +1,29 @@
 import { useState, useEffect, useCallback, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { listen, UnlistenFn } from '@tauri-apps/api/event';
-import { Project, Settings, ProcessStatus } from '../types';
+import { Project, Settings, ProcessStatus, LogEntry, LogPage, LogQuery, LogSearch, formatLogEntry } from '../types';
+import { LOG_PAGE_LINES } from '../constants';
 
 // Event payload types
 interface LogPayload {
     project_id: string;
-    log: string;
+    service?: string;
+    logs: LogEntry[];
 }
 
 interface StatusPayload {
     project_id: string;
+    service?: string;
     status: ProcessStatus;
 }
 
 interface CrashPayload {
     project_id: string;
+    service?: string;
     restart_count: number;
     will_restart: boolean;
+    retry_delay_ms: number | null;
+    next_retry_at: string | null;
 }
 
 export function useProjects() {
@@ -25,6 +31,8 @@ export function useProjects() {
     const [loading, setLoading] = useState(true);
     const [statuses, setStatuses] = useState<Record<string, ProcessStatus>>({});
     const [logs, setLogs] = useState<Record<string, string[]>>({});
+    // Sequence number of the oldest line loaded, while older ones are kept
+    const [olderLogs, setOlderLogs] = useState<Record<string, number | null>>({});
     const unlistenRefs = useRef<UnlistenFn[]>([]);
 
     // Load projects from backend
@@ -33,15 +41,15 @@ export function useProjects() {
             const data = await invoke<Project[]>('get_projects');
             setProjects(data);
             
-            // Initialize statuses for each project
-            for (const project of data) {
+            // Initialize statuses and the latest page of logs for each project
+            await Promise.all(data.map(async project => {
                 const status = await invoke<string>('get_project_status', { projectId: project.id });
                 setStatuses(prev => ({ ...prev, [project.id]: status as ProcessStatus }));
-                
-                // Load existing logs
-                const projectLogs = await invoke<string[]>('get_project_logs', { projectId: project.id });
-                setLogs(prev => ({ ...prev, [project.id]: projectLogs }));
-            }
+
+                const page = await invoke<LogPage>('get_log_page', { projectId: project.id, limit: LOG_PAGE_LINES });
+                setLogs(prev => ({ ...prev, [project.id]: page.entries.map(formatLogEntry) }));
+                setOlderLogs(prev => ({ ...prev, [project.id]: page.has_more ? page.entries[0].seq : null }));
+            }));
         } catch (error) {
             console.error('Failed to load projects:', error);
         } finally {
@@ -54,23 +62,27 @@ export function useProjects() {
         const setupListeners = async () => {
             // Listen for log events
             const unlistenLog = await listen<LogPayload>('process-log', (event) => {
-                const { project_id, log } = event.payload;
+                const { project_id, service, logs } = event.payload;
+                const lines = logs.map(entry => service ? `[${service}] ${formatLogEntry(entry)}` : formatLogEntry(entry));
                 setLogs(prev => ({
                     ...prev,
-                    [project_id]: [...(prev[project_id] || []), log].slice(-1000), // Keep last 1000 logs
+                    [project_id]: [...(prev[project_id] || []), ...lines].slice(-1000), // Keep last 1000 logs
                 }));
             });
 
             // Listen for status events
             const unlistenStatus = await listen<StatusPayload>('process-status', (event) => {
-                const { project_id, status } = event.payload;
+                const { project_id, service, status } = event.payload;
+                // Each service change is followed by the project's combined status
+                if (service) return;
                 setStatuses(prev => ({ ...prev, [project_id]: status }));
             });
 
             // Listen for crash events
             const unlistenCrash = await listen<CrashPayload>('process-crash', (event) => {
-                const { project_id, restart_count, will_restart } = event.payload;
-                console.log(`Process ${project_id} crashed. Restart count: ${restart_count}, Will restart: ${will_restart}`);
+                const { project_id, service, restart_count, will_restart, next_retry_at } = event.payload;
+                const unit = service ? `${project_id}/${service}` : project_id;
+                console.log(`Process ${unit} crashed. Restart count: ${restart_count}, Will restart: ${will_restart}, Next retry: ${next_retry_at ?? 'none'}`);
             });
 
             unlistenRefs.current = [unlistenLog, unlistenStatus, unlistenCrash];
@@ -143,13 +155,32 @@ export function useProjects() {
     const clearLogs = async (projectId: string) => {
         await invoke('clear_project_logs', { projectId });
         setLogs(prev => ({ ...prev, [projectId]: [] }));
+        setOlderLogs(prev => ({ ...prev, [projectId]: null }));
     };
 
+    // Prepend the page of logs before the oldest line loaded
+    const loadOlderLogs = async (projectId: string) => {
+        const cursor = olderLogs[projectId];
+        if (cursor == null) return;
+        const page = await invoke<LogPage>('get_log_page', {
+            projectId,
+            cursor,
+            direction: 'before',
+            limit: LOG_PAGE_LINES,
+        });
+        setLogs(prev => ({ ...prev, [projectId]: [...page.entries.map(formatLogEntry), ...(prev[projectId] || [])] }));
+        setOlderLogs(prev => ({ ...prev, [projectId]: page.has_more ? page.entries[0].seq : null }));
+    };
+
+    // Search the logs of all or some projects, in memory and on disk
+    const searchLogs = (query: LogQuery) => invoke<LogSearch>('search_logs', { query });
+
     return {
         projects,
         loading,
         statuses,
         logs,
+        olderLogs,
         addProject,
         updateProject,
         deleteProject,
@@ -157,6 +188,8 @@ export function useProjects() {
         stopProject,
         restartProject,
         clearLogs,
+        loadOlderLogs,
+        searchLogs,
         refreshProjects: loadProjects,
     };
 }
@@ -167,6 +200,7 @@ export function useSettings() {
         theme: 'dark',
         minimize_to_tray: true,
         show_notifications: true,
+        default_shell: 'bash',
     });
 
     const loadSettings = useCallback(async () => {
@@ -1,5 +1,7 @@
 // TypeScript interfaces matching Rust structs
 
+export type ShellProfile = 'bash' | 'sh' | 'zsh' | 'fish' | 'pwsh' | 'direct';
+
 export interface Project {
   id: string;
   name: string;
@@ -8,7 +10,140 @@ export interface Project {
   auto_start: boolean;
   restart_on_crash: boolean;
   enabled: boolean;
+  setup?: string[];
+  setup_on_restart?: boolean;
+  cleanup?: string[];
+  hooks?: Hooks;
   env_vars: Record<string, string>;
+  env_files?: string[];
+  shell?: ShellProfile | null;
+  depends_on?: string[];
+  health?: HealthChecks;
+  restart_policy?: RestartPolicy;
+  exit_codes?: ExitCodes;
+  stop_signal?: StopSignal;
+  stop_timeout_ms?: number;
+  // Log lines kept in memory per project or service (default 1000)
+  log_lines?: number;
+  // Output lines logged per second; more are dropped and counted (default 1000, 0 for no limit)
+  max_log_rate?: number;
+  pty?: PtyOptions | null;
+  limits?: ResourceLimits;
+  schedule?: Schedule | null;
+  watch?: WatchConfig | null;
+  services?: Service[];
+}
+
+// Shell commands run at lifecycle points, with DEVBOOT_* env vars
+export interface Hooks {
+  before_start?: string | null;
+  after_start?: string | null;
+  on_crash?: string | null;
+  on_give_up?: string | null;
+  after_stop?: string | null;
+  timeout_ms?: number;
+}
+
+// Runs instead of the project's commands, supervised on its own
+export interface Service {
+  name: string;
+  commands: string[];
+  env_vars?: Record<string, string>;
+  restart_on_crash?: boolean | null;
+  restart_policy?: RestartPolicy | null;
+  exit_codes?: ExitCodes | null;
+  health?: HealthChecks;
+  setup?: string[];
+  cleanup?: string[];
+}
+
+// Globs are relative to the project path
+export interface WatchConfig {
+  include?: string[];
+  exclude?: string[];
+  debounce_ms?: number;
+}
+
+export type CatchUp = 'skip' | 'run_once';
+
+// Cron expressions (five fields, local time)
+export interface Schedule {
+  run?: string | null;
+  restart?: string | null;
+  active_during?: string | null;
+  catch_up?: CatchUp;
+}
+
+export type LimitAction = 'log' | 'restart' | 'stop';
+
+export interface ResourceLimits {
+  max_memory_mb?: number | null;
+  max_open_files?: number | null;
+  nice?: number | null;
+  max_runtime_secs?: number | null;
+  action?: LimitAction;
+}
+
+export interface PtyOptions {
+  cols: number;
+  rows: number;
+}
+
+export type ControlKey = 'interrupt' | 'suspend' | 'eof';
+
+// One sample of a project's process tree, as returned by get_project_stats
+// and emitted as `process-stats`
+export interface ResourceSample {
+  timestamp: string;
+  cpu_percent: number;
+  rss_bytes: number;
+  threads: number;
+  children: number;
+}
+
+export type StopSignal = 'SIGTERM' | 'SIGINT' | 'SIGHUP' | 'SIGQUIT' | 'SIGKILL';
+
+export type RestartMode = 'never' | 'on_failure' | 'always';
+
+export interface RestartPolicy {
+  mode: RestartMode;
+  max_attempts: number | null;
+  initial_delay_ms: number;
+  multiplier: number;
+  max_delay_ms: number;
+  reset_after_secs: number;
+  circuit_breaker?: CircuitBreaker | null;
+}
+
+// Backs off for cool_off_secs after max_crashes within window_secs
+export interface CircuitBreaker {
+  max_crashes?: number;
+  window_secs?: number;
+  cool_off_secs?: number;
+}
+
+// Signal terminations always count as crashes
+export interface ExitCodes {
+  success?: number[];
+  no_restart?: number[];
+}
+
+export type ProbeCheck =
+  | { type: 'http'; url: string; expected_status?: number }
+  | { type: 'tcp'; host?: string; port: number }
+  | { type: 'log_pattern'; pattern: string }
+  | { type: 'command'; command: string };
+
+export type Probe = ProbeCheck & {
+  interval_ms?: number;
+  timeout_ms?: number;
+  initial_delay_ms?: number;
+  failure_threshold?: number;
+};
+
+export interface HealthChecks {
+  readiness?: Probe | null;
+  liveness?: Probe | null;
 }
 
 export interface Settings {
@@ -16,6 +151,25 @@ export interface Settings {
   theme: string;
   minimize_to_tray: boolean;
   show_notifications: boolean;
+  default_shell: ShellProfile;
+  api?: ApiSettings;
+  logs?: LogSettings;
+}
+
+// Project output written to logs/ in the config directory
+export interface LogSettings {
+  persist: boolean;
+  max_file_mb: number;
+  max_file_hours: number;
+  compress: boolean;
+  retention_days: number;
+  max_total_mb: number;
+}
+
+// Local HTTP API on 127.0.0.1, applied on the next launch
+export interface ApiSettings {
+  enabled: boolean;
+  port: number;
 }
 
 export interface AppConfig {
@@ -24,23 +178,130 @@ export interface AppConfig {
   projects: Project[];
 }
 
-export type ProcessStatus = 'stopped' | 'running' | 'error' | 'restarting';
+export type ProcessStatus =
+  | 'stopped'
+  | 'setup'
+  | 'starting'
+  | 'running'
+  | 'healthy'
+  | 'unhealthy'
+  | 'error'
+  | 'restarting'
+  | 'backoff'
+  | 'cleanup';
+
+// Statuses in which the project's process, or one of its steps, is alive
+export const isActiveStatus = (status: ProcessStatus | undefined) =>
+  status === 'setup' || status === 'starting' || status === 'running' ||
+  status === 'healthy' || status === 'unhealthy' || status === 'cleanup';
+
+export type LogStream = 'stdout' | 'stderr' | 'stdin' | 'system';
+
+// Only DevBoot's own lines are ever errors
+export type LogLevel = 'info' | 'error';
+
+// One line of a project's or service's log
+export interface LogEntry {
+  // Increases by one per line of the unit, across sessions
+  seq: number;
+  // RFC 3339
+  timestamp: string;
+  stream: LogStream;
+  level: LogLevel;
+  // Increases on every spawn, including setup and cleanup steps
+  run_id: number;
+  text: string;
+}
+
+// "[HH:MM:SS] text", with input shown as "> text" and errors as "error: text"
+export const formatLogEntry = (entry: LogEntry) => {
+  const time = entry.timestamp.slice(11, 19);
+  if (entry.stream === 'stdin') return `[${time}] > ${entry.text}`;
+  if (entry.level === 'error') return `[${time}] error: ${entry.text}`;
+  return `[${time}] ${entry.text}`;
+};
+
+export type LogDirection = 'before' | 'after';
+
+// Result of get_log_page; cursors are sequence numbers
+export interface LogPage {
+  entries: LogEntry[];
+  has_more: boolean;
+  first_seq: number | null;
+  next_seq: number;
+}
+
+// Query of search_logs; only pattern is required
+export interface LogQuery {
+  pattern: string;
+  regex?: boolean;
+  case_sensitive?: boolean;
+  // Ids or names; all projects when empty
+  project_ids?: string[];
+  streams?: LogStream[];
+  // RFC 3339
+  since?: string | null;
+  until?: string | null;
+  context?: number;
+  limit?: number;
+}
+
+export interface LogMatch {
+  project_id: string;
+  service?: string;
+  entry: LogEntry;
+  before: LogEntry[];
+  after: LogEntry[];
+}
+
+export interface LogSearch {
+  matches: LogMatch[];
+  truncated: boolean;
+}
 
 // Event payload types
+// Lines are batched, oldest first
 export interface LogPayload {
   project_id: string;
-  log: string;
+  service?: string;
+  logs: LogEntry[];
 }
 
 export interface StatusPayload {
   project_id: string;
+  service?: string;
   status: ProcessStatus;
+  // Setup or cleanup step that is running or failed, e.g. "setup 1/2: npm ci"
+  step?: string;
 }
 
 export interface CrashPayload {
   project_id: string;
   restart_count: number;
+  // null when the process was killed by a signal
+  exit_code: number | null;
+  signal: string | null;
+  core_dumped: boolean;
   will_restart: boolean;
+  retry_delay_ms: number | null;
+  next_retry_at: string | null;
+}
+
+export interface CrashRecord {
+  at: string;
+  exit_code: number | null;
+  signal: string | null;
+  core_dumped: boolean;
+}
+
+// Returned by get_breaker_status and emitted as `process-breaker`
+export interface BreakerPayload {
+  project_id: string;
+  service?: string;
+  state: 'closed' | 'open';
+  trips: number;
+  next_attempt_at: string | null;
+  crashes: CrashRecord[];
 }
 
 // Detection types